@@ -1,7 +1,6 @@
 #![allow(unused_variables)]
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use num_derive::{FromPrimitive, ToPrimitive};
 
 use tokidator::rbac::json_discriminant_array_to_vec;
 
@@ -0,0 +1,423 @@
+//! Actix-web integration, available with `actix-web` feature
+//!
+//! Register [`Authenticator`] of the access token type as application data, with either
+//! validator. [`Authenticated`] extractor validates the `Authorization: Bearer` header,
+//! [`Authorized`] extractor additionally checks it against a [`Requirement`], and
+//! [`Authorize`] middleware protects a whole scope or resource with a [`Predicate`].
+//!
+//! ```ignore
+//! App::new()
+//!     .app_data(Authenticator::<MyAccessToken>::new(validator.clone()))
+//!     .service(
+//!         web::scope("/orders")
+//!             .wrap(Authorize::<MyAccessToken>::new(Predicate::contains(ViewOrders)))
+//!             .route("", web::get().to(list_orders)),
+//!     )
+//!
+//! async fn create_order(token: Authorized<MyAccessToken, CanEditOrders>) { ... }
+//! ```
+
+use std::fmt::{self, Display};
+use std::future::{ready, Future, Ready};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use actix_web::body::EitherBody;
+use actix_web::dev::{forward_ready, Payload, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::error::ErrorInternalServerError;
+use actix_web::http::{header, StatusCode};
+use actix_web::{FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+
+use crate::bearer::{bearer_token, www_authenticate};
+use crate::rbac::{Predicate, Requirement};
+use crate::token::{AccessToken, TokenValidator, ValidationConfig, VerificationKeys};
+use crate::Error;
+
+/// Application data which validates bearer tokens of type `A`
+///
+/// Works with either validator, e.g. `HmacTokenValidator` for internal services.
+pub struct Authenticator<A: AccessToken> {
+    validator: Arc<dyn TokenAuthenticator<A>>,
+}
+
+impl<A: AccessToken> Authenticator<A> {
+    pub fn new<K>(validator: Arc<TokenValidator<K>>) -> Self
+    where
+        K: VerificationKeys + 'static,
+    {
+        Self::with_config(validator, ValidationConfig::default())
+    }
+
+    pub fn with_config<K>(validator: Arc<TokenValidator<K>>, config: ValidationConfig) -> Self
+    where
+        K: VerificationKeys + 'static,
+    {
+        Self {
+            validator: Arc::new(ConfiguredValidator { validator, config }),
+        }
+    }
+
+    fn authenticate(&self, request: &HttpRequest) -> Result<A, Error> {
+        let token = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| bearer_token(value.as_bytes()))
+            .ok_or(Error::MissingAccessToken)?;
+        self.validator.validate(token)
+    }
+}
+
+impl<A: AccessToken> Clone for Authenticator<A> {
+    fn clone(&self) -> Self {
+        Self {
+            validator: self.validator.clone(),
+        }
+    }
+}
+
+/// Validation and authorization of [`Authenticator`], whatever keys it verifies with
+trait TokenAuthenticator<A: AccessToken> {
+    fn validate(&self, token: &[u8]) -> Result<A, Error>;
+    fn is_authorized(&self, access_token: &A, predicate: &Predicate<A::Permission>) -> bool;
+}
+
+struct ConfiguredValidator<K> {
+    validator: Arc<TokenValidator<K>>,
+    config: ValidationConfig,
+}
+
+impl<A: AccessToken, K: VerificationKeys> TokenAuthenticator<A> for ConfiguredValidator<K> {
+    fn validate(&self, token: &[u8]) -> Result<A, Error> {
+        self.validator
+            .validate_config(token, &self.config)
+            .map_err(Error::from)
+    }
+
+    fn is_authorized(&self, access_token: &A, predicate: &Predicate<A::Permission>) -> bool {
+        self.validator
+            .authorize_config(access_token, predicate, &self.config)
+            .is_granted()
+    }
+}
+
+/// Validate the bearer token of the request, once per request
+fn authenticate<A>(request: &HttpRequest) -> Result<A, actix_web::Error>
+where
+    A: AccessToken + Clone + 'static,
+{
+    if let Some(access_token) = request.extensions().get::<A>() {
+        return Ok(access_token.clone());
+    }
+    let access_token = authenticator::<A>(request)?
+        .authenticate(request)
+        .map_err(AuthError)?;
+    request.extensions_mut().insert(access_token.clone());
+    Ok(access_token)
+}
+
+fn authorize<A>(
+    request: &HttpRequest,
+    predicate: &Predicate<A::Permission>,
+) -> Result<A, actix_web::Error>
+where
+    A: AccessToken + Clone + 'static,
+{
+    let access_token = authenticate::<A>(request)?;
+    if !authenticator::<A>(request)?
+        .validator
+        .is_authorized(&access_token, predicate)
+    {
+        return Err(AuthError(Error::Unauthorized).into());
+    }
+    Ok(access_token)
+}
+
+fn authenticator<A>(request: &HttpRequest) -> Result<&Authenticator<A>, actix_web::Error>
+where
+    A: AccessToken + 'static,
+{
+    request
+        .app_data::<Authenticator<A>>()
+        .ok_or_else(|| ErrorInternalServerError("authenticator is not configured"))
+}
+
+/// Validated access token
+pub struct Authenticated<A>(pub A);
+
+impl<A> Authenticated<A> {
+    pub fn into_inner(self) -> A {
+        self.0
+    }
+}
+
+impl<A> Deref for Authenticated<A> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        &self.0
+    }
+}
+
+impl<A> FromRequest for Authenticated<A>
+where
+    A: AccessToken + Clone + 'static,
+{
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(authenticate(request).map(Self))
+    }
+}
+
+/// Validated access token which satisfies the predicate of `R`
+pub struct Authorized<A, R> {
+    access_token: A,
+    _requirement: PhantomData<fn() -> R>,
+}
+
+impl<A, R> Authorized<A, R> {
+    pub fn into_inner(self) -> A {
+        self.access_token
+    }
+}
+
+impl<A, R> Deref for Authorized<A, R> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        &self.access_token
+    }
+}
+
+impl<A, R> FromRequest for Authorized<A, R>
+where
+    A: AccessToken + Clone + 'static,
+    R: Requirement<Permission = A::Permission>,
+{
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(request: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(
+            authorize(request, &R::predicate()).map(|access_token| Self {
+                access_token,
+                _requirement: PhantomData,
+            }),
+        )
+    }
+}
+
+/// Middleware which rejects requests whose access token does not satisfy the predicate
+///
+/// The validated access token is available to handlers through [`Authenticated`].
+pub struct Authorize<A: AccessToken> {
+    predicate: Rc<Predicate<A::Permission>>,
+}
+
+impl<A: AccessToken> Authorize<A> {
+    pub fn new(predicate: Predicate<A::Permission>) -> Self {
+        Self {
+            predicate: Rc::new(predicate),
+        }
+    }
+}
+
+impl<S, B, A> Transform<S, ServiceRequest> for Authorize<A>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+    A: AccessToken + Clone + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Transform = AuthorizeMiddleware<S, A>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthorizeMiddleware {
+            service,
+            predicate: self.predicate.clone(),
+        }))
+    }
+}
+
+/// Service created by [`Authorize`]
+pub struct AuthorizeMiddleware<S, A: AccessToken> {
+    service: S,
+    predicate: Rc<Predicate<A::Permission>>,
+}
+
+impl<S, B, A> Service<ServiceRequest> for AuthorizeMiddleware<S, A>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: 'static,
+    A: AccessToken + Clone + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = actix_web::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, request: ServiceRequest) -> Self::Future {
+        if let Err(error) = authorize::<A>(request.request(), &self.predicate) {
+            let response = request.error_response(error).map_into_right_body();
+            return Box::pin(ready(Ok(response)));
+        }
+        let response = self.service.call(request);
+        Box::pin(async move { Ok(response.await?.map_into_left_body()) })
+    }
+}
+
+/// Rejection of extractors and middleware, see [`Error::is_forbidden`] for its status
+#[derive(Debug, Clone, Copy)]
+pub struct AuthError(pub Error);
+
+impl Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        if self.0.is_forbidden() {
+            StatusCode::FORBIDDEN
+        } else {
+            StatusCode::UNAUTHORIZED
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code())
+            .insert_header((header::WWW_AUTHENTICATE, www_authenticate(self.0)))
+            .body(self.0.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{web, App};
+
+    use crate::crypto::{Algorithm, HmacKey};
+    use crate::rbac::test_helpers::TestPermission::{Permission1, Permission2};
+    use crate::token::test_utils::{issue, make_validator, CanUsePermission2, TestAccessToken};
+    use crate::token::{HmacTokenValidator, TokenIssuer};
+
+    use super::*;
+
+    async fn send(uri: &str, authorization: Option<&str>) -> (StatusCode, String) {
+        send_with(
+            Authenticator::new(Arc::new(make_validator())),
+            uri,
+            authorization,
+        )
+        .await
+    }
+
+    async fn send_with(
+        authenticator: Authenticator<TestAccessToken>,
+        uri: &str,
+        authorization: Option<&str>,
+    ) -> (StatusCode, String) {
+        let app = init_service(
+            App::new()
+                .app_data(authenticator)
+                .route(
+                    "/token",
+                    web::get().to(|token: Authenticated<TestAccessToken>| async move {
+                        token.permissions().iter().count().to_string()
+                    }),
+                )
+                .route(
+                    "/authorized",
+                    web::get()
+                        .to(|_: Authorized<TestAccessToken, CanUsePermission2>| async { "ok" }),
+                )
+                .service(
+                    web::scope("/scope")
+                        .wrap(Authorize::<TestAccessToken>::new(Predicate::contains(
+                            Permission1,
+                        )))
+                        .route(
+                            "",
+                            web::get().to(|_: Authenticated<TestAccessToken>| async { "ok" }),
+                        ),
+                ),
+        )
+        .await;
+
+        let mut request = TestRequest::get().uri(uri);
+        if let Some(authorization) = authorization {
+            request = request.insert_header((header::AUTHORIZATION, authorization));
+        }
+        let response = call_service(&app, request.to_request()).await;
+        let www_authenticate = response
+            .headers()
+            .get(header::WWW_AUTHENTICATE)
+            .map_or("", |value| value.to_str().unwrap())
+            .to_owned();
+        (response.status(), www_authenticate)
+    }
+
+    #[actix_web::test]
+    async fn extractors_should_check_token() {
+        let token = issue(TestAccessToken::new(vec![Permission2].into(), false));
+        let authorization = format!("Bearer {token}");
+        for uri in ["/token", "/authorized"] {
+            let (status, _) = send(uri, Some(&authorization)).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (status, www_authenticate) = send("/token", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate, "Bearer");
+    }
+
+    #[actix_web::test]
+    async fn middleware_should_check_predicate() {
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let (status, _) = send("/scope", Some(&format!("Bearer {token}"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let token = issue(TestAccessToken::new(vec![Permission2].into(), false));
+        let (status, www_authenticate) = send("/scope", Some(&format!("Bearer {token}"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(www_authenticate, "Bearer error=\"insufficient_scope\"");
+
+        let (status, www_authenticate) = send("/scope", Some("Bearer 123")).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(www_authenticate, "Bearer error=\"invalid_token\"");
+    }
+
+    #[actix_web::test]
+    async fn authenticator_should_validate_with_shared_secret() {
+        let hmac_key = || HmacKey::new(Algorithm::HmacSha256, &[7; 32]).unwrap();
+        let validator = Arc::new(HmacTokenValidator::with_hmac_key(hmac_key()));
+        let token = TokenIssuer::new(hmac_key())
+            .issue(TestAccessToken::new(vec![Permission2].into(), false));
+        let authenticator = Authenticator::new(validator.clone());
+        let (status, _) = send_with(
+            authenticator,
+            "/authorized",
+            Some(&format!("Bearer {token}")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+
+        let token = issue(TestAccessToken::new(vec![Permission2].into(), false));
+        let authenticator = Authenticator::new(validator);
+        let (status, _) = send_with(
+            authenticator,
+            "/authorized",
+            Some(&format!("Bearer {token}")),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,218 @@
+//! Audit trail of token validation and authorization decisions
+//!
+//! `TokenValidator` records every validation and every
+//! [`authorize`](crate::token::TokenValidator::authorize) call to the sink set by
+//! [`set_audit_sink`](crate::token::TokenValidator::set_audit_sink). Framework integrations
+//! authorize through it.
+//!
+//! Access tokens are authorized through `TokenValidator::authorize` only,
+//! `AccessToken::is_authorized` and `AccessToken::authorize` are deprecated as their decisions
+//! never reach the sink. [`Predicate`](crate::rbac::Predicate) still evaluates plain
+//! permission sets, e.g. of roles, which are not decisions about a token.
+//!
+//! Permissions are recorded by discriminant, as `Permission` does not require `Display`,
+//! e.g. predicate `1 & !2` and reason `{"missing": [1]}`.
+
+use std::fmt::{self, Display};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+/// Destination of audit events
+pub trait AuditSink: Send + Sync {
+    fn record(&self, event: &AuditEvent);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Validation,
+    Authorization,
+}
+
+/// Single validation or authorization decision
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct AuditEvent {
+    pub time: SystemTime,
+    pub action: AuditAction,
+    pub subject: Option<String>,
+    pub token_id: Option<String>,
+    /// Required predicate of authorization
+    pub predicate: Option<String>,
+    pub granted: bool,
+    /// Why validation failed or authorization was denied
+    ///
+    /// `{"error": .., "stage": ..}` for validation and the explanation of
+    /// [`Denial`](crate::rbac::Denial) for authorization.
+    pub reason: Option<Value>,
+}
+
+impl AuditEvent {
+    pub(crate) fn new(action: AuditAction, granted: bool, time: SystemTime) -> Self {
+        Self {
+            time,
+            action,
+            subject: None,
+            token_id: None,
+            predicate: None,
+            granted,
+            reason: None,
+        }
+    }
+
+    /// Structured form, `time` is in seconds since Unix epoch
+    pub fn to_json(&self) -> Value {
+        let time = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        json!({
+            "time": time,
+            "action": self.action.to_string(),
+            "subject": self.subject,
+            "token_id": self.token_id,
+            "predicate": self.predicate,
+            "granted": self.granted,
+            "reason": self.reason,
+        })
+    }
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            AuditAction::Validation => f.write_str("validation"),
+            AuditAction::Authorization => f.write_str("authorization"),
+        }
+    }
+}
+
+/// Emit events as `tracing` events with target `tokidator::audit`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TracingSink;
+
+impl AuditSink for TracingSink {
+    fn record(&self, event: &AuditEvent) {
+        info!(
+            target: "tokidator::audit",
+            action = %event.action,
+            subject = event.subject.as_deref(),
+            token_id = event.token_id.as_deref(),
+            predicate = event.predicate.as_deref(),
+            granted = event.granted,
+            reason = event.reason.as_ref().map(tracing::field::display),
+        );
+    }
+}
+
+/// Write events as JSON lines
+///
+/// Write errors are logged and the event is dropped.
+#[derive(Debug)]
+pub struct JsonLinesSink<W> {
+    writer: Mutex<W>,
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<W: Write + Send> AuditSink for JsonLinesSink<W> {
+    fn record(&self, event: &AuditEvent) {
+        let mut line = event.to_json().to_string();
+        line.push('\n');
+        let mut writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Err(error) = writer
+            .write_all(line.as_bytes())
+            .and_then(|_| writer.flush())
+        {
+            warn!("Failed to write audit event: {}", error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::rbac::test_helpers::TestPermission::{Permission1, Permission2};
+    use crate::rbac::Predicate;
+    use crate::token::test_utils::{make_validator, private_key, TestAccessToken};
+    use crate::token::{TokenIssuer, ValidationConfig};
+
+    use super::*;
+
+    fn clock() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(4_000_000_000)
+    }
+
+    #[test]
+    fn json_lines_sink_should_record_decisions() {
+        let sink = Arc::new(JsonLinesSink::new(Vec::new()));
+        let mut validator = make_validator();
+        validator.set_audit_sink(Some(sink.clone()));
+
+        let issuer = TokenIssuer::new(private_key());
+        let token = issuer.issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let config = ValidationConfig {
+            clock,
+            ..Default::default()
+        };
+        let access_token: TestAccessToken = validator.validate_config(&token, &config).unwrap();
+        let predicate = Predicate::contains(Permission1);
+        let decision = validator.authorize_config(&access_token, &predicate, &config);
+        assert!(decision.is_granted());
+        let predicate = Predicate::all([Permission1, Permission2]);
+        let decision = validator.authorize_config(&access_token, &predicate, &config);
+        assert!(!decision.is_granted());
+        let expired = issuer.issue(TestAccessToken::new(vec![Permission1].into(), true));
+        let x = validator.validate_config::<TestAccessToken, _>(&expired, &config);
+        assert!(x.is_err());
+
+        drop(validator);
+        let output = Arc::try_unwrap(sink).unwrap().into_inner();
+        let events: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 4);
+        assert!(events.iter().all(|event| event["time"] == 4_000_000_000u64));
+
+        assert_eq!(events[0]["action"], "validation");
+        assert_eq!(events[0]["granted"], true);
+        assert_eq!(events[0]["reason"], Value::Null);
+
+        assert_eq!(events[1]["action"], "authorization");
+        assert_eq!(events[1]["predicate"], "1");
+        assert_eq!(events[1]["granted"], true);
+
+        assert_eq!(events[2]["predicate"], "1 & 2");
+        assert_eq!(events[2]["granted"], false);
+        assert_eq!(events[2]["reason"], json!({ "missing": [2] }));
+
+        assert_eq!(events[3]["action"], "validation");
+        assert_eq!(events[3]["granted"], false);
+        assert_eq!(
+            events[3]["reason"],
+            json!({ "error": "expired access token", "stage": "claims" })
+        );
+    }
+}
@@ -0,0 +1,334 @@
+//! Axum and tower integration, available with `axum` feature
+//!
+//! [`AuthLayer`] validates the `Authorization: Bearer` header of every request and puts the
+//! validated access token into request extensions, where handlers can take it with
+//! `Extension<A>`. [`Authorized`] extractor additionally checks it against a [`Requirement`].
+//! The layer works with either validator, [`Authorized`] does not depend on its keys.
+//!
+//! ```ignore
+//! let app = Router::new()
+//!     .route("/orders", post(create_order))
+//!     .route_layer(AuthLayer::<MyAccessToken>::new(validator));
+//!
+//! async fn create_order(token: Authorized<MyAccessToken, CanEditOrders>) { ... }
+//! ```
+
+use std::future::Future;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderMap, HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::bearer::{bearer_token, www_authenticate};
+use crate::rbac::{Predicate, Requirement};
+use crate::token::{AccessToken, KeyRing, TokenValidator, ValidationConfig, VerificationKeys};
+use crate::Error;
+
+/// Layer which rejects requests without a valid bearer token
+///
+/// Apply it with `route_layer` to the routes which require authentication. Validates with
+/// keys `K` of the validator, e.g. `HmacKey` of `HmacTokenValidator`.
+pub struct AuthLayer<A, K = KeyRing> {
+    validator: Arc<LayerValidator<K>>,
+    _token: PhantomData<fn() -> A>,
+}
+
+impl<A, K> AuthLayer<A, K> {
+    pub fn new(validator: Arc<TokenValidator<K>>) -> Self {
+        Self::with_config(validator, ValidationConfig::default())
+    }
+
+    pub fn with_config(validator: Arc<TokenValidator<K>>, config: ValidationConfig) -> Self {
+        Self {
+            validator: Arc::new(LayerValidator { validator, config }),
+            _token: PhantomData,
+        }
+    }
+}
+
+impl<A, K> Clone for AuthLayer<A, K> {
+    fn clone(&self) -> Self {
+        Self {
+            validator: self.validator.clone(),
+            _token: PhantomData,
+        }
+    }
+}
+
+impl<S, A, K> Layer<S> for AuthLayer<A, K> {
+    type Service = AuthService<S, A, K>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Validator and config shared by clones of [`AuthLayer`]
+struct LayerValidator<K> {
+    validator: Arc<TokenValidator<K>>,
+    config: ValidationConfig,
+}
+
+/// Authorization with the validator of [`AuthLayer`], whatever keys it verifies with
+trait Authorizer<A: AccessToken>: Send + Sync {
+    fn is_authorized(&self, access_token: &A, predicate: &Predicate<A::Permission>) -> bool;
+}
+
+impl<A, K> Authorizer<A> for LayerValidator<K>
+where
+    A: AccessToken,
+    K: VerificationKeys + Send + Sync,
+{
+    fn is_authorized(&self, access_token: &A, predicate: &Predicate<A::Permission>) -> bool {
+        self.validator
+            .authorize_config(access_token, predicate, &self.config)
+            .is_granted()
+    }
+}
+
+/// Request extension through which [`Authorized`] reaches the validator of [`AuthLayer`]
+struct LayerAuthorizer<A: AccessToken>(Arc<dyn Authorizer<A>>);
+
+impl<A: AccessToken> Clone for LayerAuthorizer<A> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Service created by [`AuthLayer`]
+pub struct AuthService<S, A, K = KeyRing> {
+    inner: S,
+    layer: AuthLayer<A, K>,
+}
+
+impl<S: Clone, A, K> Clone for AuthService<S, A, K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, A, K> AuthService<S, A, K>
+where
+    A: AccessToken,
+    K: VerificationKeys,
+{
+    fn authenticate(&self, headers: &HeaderMap) -> Result<A, Error> {
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| bearer_token(value.as_bytes()))
+            .ok_or(Error::MissingAccessToken)?;
+        let LayerValidator { validator, config } = &*self.layer.validator;
+        validator
+            .validate_config(token, config)
+            .map_err(Error::from)
+    }
+}
+
+impl<S, A, K, B> Service<Request<B>> for AuthService<S, A, K>
+where
+    S: Service<Request<B>, Response = Response>,
+    S::Future: Send + 'static,
+    A: AccessToken + Clone + Send + Sync + 'static,
+    K: VerificationKeys + Send + Sync + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        match self.authenticate(request.headers()) {
+            Ok(access_token) => {
+                let extensions = request.extensions_mut();
+                extensions.insert(access_token);
+                extensions.insert(LayerAuthorizer::<A>(self.layer.validator.clone()));
+                Box::pin(self.inner.call(request))
+            }
+            Err(error) => {
+                let response = AuthRejection(error).into_response();
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+/// Validated access token which satisfies the predicate of `R`
+///
+/// Requires [`AuthLayer`] of the same access token type on the route, otherwise responds
+/// with 500. Other rejections are [`AuthRejection`] responses.
+pub struct Authorized<A, R> {
+    access_token: A,
+    _requirement: PhantomData<fn() -> R>,
+}
+
+impl<A, R> Authorized<A, R> {
+    pub fn into_inner(self) -> A {
+        self.access_token
+    }
+}
+
+impl<A, R> Deref for Authorized<A, R> {
+    type Target = A;
+
+    fn deref(&self) -> &A {
+        &self.access_token
+    }
+}
+
+impl<S, A, R> FromRequestParts<S> for Authorized<A, R>
+where
+    S: Send + Sync,
+    A: AccessToken + Clone + Send + Sync + 'static,
+    R: Requirement<Permission = A::Permission>,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        // AuthLayer rejects requests without valid token, so missing one is misconfiguration
+        let (access_token, LayerAuthorizer(authorizer)) = parts
+            .extensions
+            .get::<A>()
+            .cloned()
+            .zip(parts.extensions.get::<LayerAuthorizer<A>>())
+            .ok_or_else(|| {
+                let message = "auth layer is not configured";
+                (StatusCode::INTERNAL_SERVER_ERROR, message).into_response()
+            })?;
+        if !authorizer.is_authorized(&access_token, &R::predicate()) {
+            return Err(AuthRejection(Error::Unauthorized).into_response());
+        }
+        Ok(Self {
+            access_token,
+            _requirement: PhantomData,
+        })
+    }
+}
+
+/// Rejection of [`AuthLayer`] and [`Authorized`], 403 if [`Error::is_forbidden`] and 401
+/// otherwise
+#[derive(Debug, Clone, Copy)]
+pub struct AuthRejection(pub Error);
+
+impl AuthRejection {
+    pub fn status(&self) -> StatusCode {
+        if self.0.is_forbidden() {
+            StatusCode::FORBIDDEN
+        } else {
+            StatusCode::UNAUTHORIZED
+        }
+    }
+}
+
+impl IntoResponse for AuthRejection {
+    fn into_response(self) -> Response {
+        let www_authenticate = HeaderValue::from_static(www_authenticate(self.0));
+        let headers = [(header::WWW_AUTHENTICATE, www_authenticate)];
+        (self.status(), headers, self.0.to_string()).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::{Extension, Router};
+    use tower::ServiceExt;
+
+    use crate::crypto::{Algorithm, HmacKey};
+    use crate::rbac::test_helpers::TestPermission::{Permission1, Permission2};
+    use crate::token::test_utils::{issue, make_validator, CanUsePermission2, TestAccessToken};
+    use crate::token::{HmacTokenValidator, TokenIssuer};
+
+    use super::*;
+
+    fn routes() -> Router {
+        Router::new()
+            .route(
+                "/token",
+                get(|Extension(token): Extension<TestAccessToken>| async move {
+                    token.permissions().iter().count().to_string()
+                }),
+            )
+            .route(
+                "/authorized",
+                get(|_: Authorized<TestAccessToken, CanUsePermission2>| async { "ok" }),
+            )
+    }
+
+    async fn send(app: Router, uri: &str, authorization: Option<&str>) -> Response {
+        let mut request = Request::builder().uri(uri);
+        if let Some(authorization) = authorization {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        app.oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn layer_should_insert_access_token() {
+        let app = routes().route_layer(AuthLayer::<TestAccessToken>::new(Arc::new(
+            make_validator(),
+        )));
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let response = send(app.clone(), "/token", Some(&format!("Bearer {token}"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = send(app, "/token", Some("Bearer 123")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(
+            response.headers()[header::WWW_AUTHENTICATE],
+            "Bearer error=\"invalid_token\""
+        );
+    }
+
+    #[tokio::test]
+    async fn authorized_should_check_requirement() {
+        let app = routes().route_layer(AuthLayer::<TestAccessToken>::new(Arc::new(
+            make_validator(),
+        )));
+        let token = issue(TestAccessToken::new(vec![Permission2].into(), false));
+        let response = send(app.clone(), "/authorized", Some(&format!("Bearer {token}"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let response = send(app, "/authorized", Some(&format!("Bearer {token}"))).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // route without AuthLayer is misconfigured
+        let token = issue(TestAccessToken::new(vec![Permission2].into(), false));
+        let response = send(routes(), "/authorized", Some(&format!("Bearer {token}"))).await;
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[tokio::test]
+    async fn layer_should_validate_with_shared_secret() {
+        let hmac_key = || HmacKey::new(Algorithm::HmacSha256, &[7; 32]).unwrap();
+        let validator = Arc::new(HmacTokenValidator::with_hmac_key(hmac_key()));
+        let app = routes().route_layer(AuthLayer::<TestAccessToken, _>::new(validator));
+        let token = TokenIssuer::new(hmac_key())
+            .issue(TestAccessToken::new(vec![Permission2].into(), false));
+        let response = send(app.clone(), "/authorized", Some(&format!("Bearer {token}"))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let token = issue(TestAccessToken::new(vec![Permission2].into(), false));
+        let response = send(app, "/authorized", Some(&format!("Bearer {token}"))).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
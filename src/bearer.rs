@@ -0,0 +1,49 @@
+/// Extract token from `Authorization` header value of `Bearer` scheme, case-insensitive
+pub(crate) fn bearer_token(value: &[u8]) -> Option<&[u8]> {
+    let separator = value.iter().position(|&b| b == b' ')?;
+    let (scheme, token) = value.split_at(separator);
+    let token = token.trim_ascii_start();
+    (scheme.eq_ignore_ascii_case(b"Bearer") && !token.is_empty()).then_some(token)
+}
+
+/// `WWW-Authenticate` challenge of the error, as described in RFC 6750
+#[cfg(any(feature = "actix-web", feature = "axum"))]
+pub(crate) fn www_authenticate(error: crate::Error) -> &'static str {
+    use crate::Error;
+
+    match error {
+        Error::MissingAccessToken => "Bearer",
+        _ if error.is_forbidden() => "Bearer error=\"insufficient_scope\"",
+        _ => "Bearer error=\"invalid_token\"",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_bearer_token() {
+        assert_eq!(bearer_token(b"Bearer abc.def"), Some(&b"abc.def"[..]));
+        assert_eq!(bearer_token(b"bearer  abc"), Some(&b"abc"[..]));
+        assert_eq!(bearer_token(b"Bearer "), None);
+        assert_eq!(bearer_token(b"Bearer"), None);
+        assert_eq!(bearer_token(b"Basic dXNlcjpwYXNz"), None);
+    }
+
+    #[test]
+    #[cfg(any(feature = "actix-web", feature = "axum"))]
+    fn challenge_of_error() {
+        use crate::Error;
+
+        assert_eq!(www_authenticate(Error::MissingAccessToken), "Bearer");
+        assert_eq!(
+            www_authenticate(Error::ExpiredAccessToken),
+            "Bearer error=\"invalid_token\""
+        );
+        assert_eq!(
+            www_authenticate(Error::Unauthorized),
+            "Bearer error=\"insufficient_scope\""
+        );
+    }
+}
@@ -1,14 +1,45 @@
-use ring::signature::{Ed25519KeyPair, UnparsedPublicKey, ED25519};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
 
-pub struct PrivateKey(Ed25519KeyPair);
+mod algorithm;
+mod encryption;
+mod hmac;
+mod key_format;
+
+pub use self::hmac::HmacKey;
+pub use algorithm::{Algorithm, EcdsaPrivateKey, RsaPrivateKey, Signer, Verifier, VerifyingKey};
+pub use encryption::{EncryptedMessage, EncryptionKey};
+
+const SEED_LEN: usize = 32;
+
+pub struct PrivateKey {
+    key_pair: Ed25519KeyPair,
+    // ring does not expose the seed of a key pair, keep it for exporting
+    seed: [u8; SEED_LEN],
+}
 
 impl PrivateKey {
+    /// Generate a new key from the operating system's secure random number generator
+    ///
+    /// Return `None` if the random number generator fails.
+    pub fn generate() -> Option<Self> {
+        let mut seed = [0u8; SEED_LEN];
+        SystemRandom::new().fill(&mut seed).ok()?;
+        Self::from_bytes(&seed)
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
-        Ed25519KeyPair::from_seed_unchecked(bytes).map(Self).ok()
+        let seed = bytes.try_into().ok()?;
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(bytes).ok()?;
+        Some(Self { key_pair, seed })
     }
 
     pub fn sign(&self, msg: &[u8]) -> Vec<u8> {
-        self.0.sign(msg).as_ref().to_vec()
+        self.key_pair.sign(msg).as_ref().to_vec()
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from_bytes(self.key_pair.public_key().as_ref())
     }
 
     pub fn from_base64<T: ?Sized + AsRef<[u8]>>(input: &T) -> Option<Self> {
@@ -16,42 +47,102 @@ impl PrivateKey {
             .ok()
             .and_then(|seed| Self::from_bytes(&seed))
     }
+
+    /// Encode seed in the format accepted by [`from_base64`](Self::from_base64)
+    pub fn to_base64(&self) -> String {
+        base64::encode_config(self.seed, base64::URL_SAFE_NO_PAD)
+    }
 }
 
 #[derive(Clone)]
-pub struct PublicKey(UnparsedPublicKey<Vec<u8>>);
+pub struct PublicKey(Vec<u8>);
 
 impl PublicKey {
     pub fn from_bytes(bytes: &[u8]) -> Self {
-        Self(UnparsedPublicKey::new(&ED25519, bytes.to_vec()))
+        Self(bytes.to_vec())
     }
 
     pub fn from_base64<T: ?Sized + AsRef<[u8]>>(input: &T) -> Option<Self> {
         base64::decode_config(input, base64::URL_SAFE_NO_PAD)
-            .map(|bytes| Self(UnparsedPublicKey::new(&ED25519, bytes)))
+            .map(Self)
             .ok()
     }
 
     pub fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
-        self.0.verify(message, signature).is_ok()
+        UnparsedPublicKey::new(&ED25519, &self.0)
+            .verify(message, signature)
+            .is_ok()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Encode in the format accepted by [`from_base64`](Self::from_base64)
+    pub fn to_base64(&self) -> String {
+        base64::encode_config(&self.0, base64::URL_SAFE_NO_PAD)
     }
 }
 
 const SEPARATOR: u8 = b'.';
+/// Format version, the first segment of encoded signed message
+const VERSION: &str = "v1";
 
 pub struct SignedMessage {
     message: Vec<u8>,
     signature: Vec<u8>,
+    key_id: Option<String>,
+    algorithm: Algorithm,
 }
 
 impl SignedMessage {
-    pub fn create(message: Vec<u8>, key: &PrivateKey) -> Self {
+    pub fn create<S: Signer + ?Sized>(message: Vec<u8>, key: &S) -> Self {
         let signature = key.sign(&message);
-        Self { message, signature }
+        Self {
+            message,
+            signature,
+            key_id: None,
+            algorithm: key.algorithm(),
+        }
     }
 
-    pub fn verify(&self, key: &PublicKey) -> bool {
-        key.verify(&self.message, &self.signature)
+    /// Assemble signed message decoded from another token format
+    pub(crate) fn from_parts(
+        message: Vec<u8>,
+        signature: Vec<u8>,
+        key_id: Option<String>,
+        algorithm: Algorithm,
+    ) -> Self {
+        Self {
+            message,
+            signature,
+            key_id,
+            algorithm,
+        }
+    }
+
+    /// Create signed message which records identifier of the signing key
+    ///
+    /// Validators holding several public keys use it to pick the right one.
+    pub fn create_with_key_id<S: Signer + ?Sized>(
+        message: Vec<u8>,
+        key_id: String,
+        key: &S,
+    ) -> Self {
+        Self {
+            key_id: Some(key_id),
+            ..Self::create(message, key)
+        }
+    }
+
+    /// Verify signature, the key must be of the algorithm recorded in the message
+    pub fn verify<V: Verifier + ?Sized>(&self, key: &V) -> bool {
+        key.algorithm() == self.algorithm && key.verify(&self.message, &self.signature)
+    }
+
+    /// Verify HMAC tag, the key must be of the algorithm recorded in the message
+    pub fn verify_hmac(&self, key: &HmacKey) -> bool {
+        key.algorithm() == self.algorithm && key.verify(&self.message, &self.signature)
     }
 
     pub fn message(&self) -> &[u8] {
@@ -62,35 +153,146 @@ impl SignedMessage {
         &self.signature
     }
 
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Encode to `v1.base64(message).base64(signature).base64(key_id).base64(algorithm name)`
+    ///
+    /// Key id segment is empty if there is no key id.
     pub fn encode(&self) -> String {
-        fn base64_encode_buf(input: &[u8], buf: &mut String) {
-            base64::encode_config_buf(input, base64::URL_SAFE_NO_PAD, buf)
+        let mut output = String::with_capacity(self.get_encoded_len());
+        output.push_str(VERSION);
+        for segment in [
+            &self.message,
+            &self.signature,
+            self.key_id.as_deref().unwrap_or_default().as_bytes(),
+            self.algorithm.name().as_bytes(),
+        ] {
+            output.push(char::from(SEPARATOR));
+            base64_encode_buf(segment, &mut output);
         }
-        let Self { message, signature } = self;
+        output
+    }
+
+    /// Encode in the unversioned format accepted by [`decode_legacy`](Self::decode_legacy)
+    ///
+    /// It is `base64(message).base64(signature)`, followed by `.base64(key_id)` if present.
+    /// Algorithms other than Ed25519 append `.base64(algorithm name)`, the key id segment is
+    /// left empty if there is no key id.
+    pub fn encode_legacy(&self) -> String {
+        let Self {
+            message,
+            signature,
+            key_id,
+            algorithm,
+        } = self;
         let mut output = String::with_capacity(self.get_encoded_len());
         base64_encode_buf(message, &mut output);
         output.push(char::from(SEPARATOR));
         base64_encode_buf(signature, &mut output);
+        if key_id.is_some() || *algorithm != Algorithm::Ed25519 {
+            output.push(char::from(SEPARATOR));
+            base64_encode_buf(
+                key_id.as_deref().unwrap_or_default().as_bytes(),
+                &mut output,
+            );
+        }
+        if *algorithm != Algorithm::Ed25519 {
+            output.push(char::from(SEPARATOR));
+            base64_encode_buf(algorithm.name().as_bytes(), &mut output);
+        }
         output
     }
 
+    /// Decode message encoded by [`encode`](Self::encode), any other segment count is rejected
     pub fn decode<T: AsRef<[u8]>>(input: T) -> Option<Self> {
         let mut iter = input.as_ref().split(|&b| b == SEPARATOR);
-        let decode = |input| base64::decode_config(input, base64::URL_SAFE_NO_PAD).ok();
+        if iter.next() != Some(VERSION.as_bytes()) {
+            return None;
+        }
+        match (
+            iter.next(),
+            iter.next(),
+            iter.next(),
+            iter.next(),
+            iter.next(),
+        ) {
+            (Some(message), Some(signature), Some(key_id), Some(algorithm), None) => {
+                Some(SignedMessage {
+                    message: base64_decode(message)?,
+                    signature: base64_decode(signature)?,
+                    key_id: decode_key_id(key_id)?,
+                    algorithm: decode_algorithm(algorithm)?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Decode message encoded by [`encode_legacy`](Self::encode_legacy)
+    ///
+    /// Only for migrating from unversioned tokens, prefer [`decode`](Self::decode).
+    pub fn decode_legacy<T: AsRef<[u8]>>(input: T) -> Option<Self> {
+        let mut iter = input.as_ref().split(|&b| b == SEPARATOR);
         match (iter.next(), iter.next()) {
-            (Some(message), Some(signature)) => Some(SignedMessage {
-                message: decode(message)?,
-                signature: decode(signature)?,
-            }),
+            (Some(message), Some(signature)) => {
+                let key_id = match iter.next() {
+                    Some(key_id) => decode_key_id(key_id)?,
+                    None => None,
+                };
+                let algorithm = match iter.next() {
+                    Some(algorithm) => decode_algorithm(algorithm)?,
+                    None => Algorithm::Ed25519,
+                };
+                if iter.next().is_some() {
+                    return None;
+                }
+                Some(SignedMessage {
+                    message: base64_decode(message)?,
+                    signature: base64_decode(signature)?,
+                    key_id,
+                    algorithm,
+                })
+            }
             _ => None,
         }
     }
 
     fn get_encoded_len(&self) -> usize {
-        char::from(SEPARATOR).len_utf8()
+        let separator_len = char::from(SEPARATOR).len_utf8();
+        let key_id = self.key_id.as_deref().unwrap_or_default();
+        VERSION.len()
+            + 4 * separator_len
             + url_safe_no_pad_len(&self.message)
             + url_safe_no_pad_len(&self.signature)
+            + url_safe_no_pad_len(key_id.as_bytes())
+            + url_safe_no_pad_len(self.algorithm.name().as_bytes())
+    }
+}
+
+fn base64_encode_buf(input: &[u8], buf: &mut String) {
+    base64::encode_config_buf(input, base64::URL_SAFE_NO_PAD, buf)
+}
+
+fn base64_decode(input: &[u8]) -> Option<Vec<u8>> {
+    base64::decode_config(input, base64::URL_SAFE_NO_PAD).ok()
+}
+
+/// Empty segment means no key id
+fn decode_key_id(input: &[u8]) -> Option<Option<String>> {
+    if input.is_empty() {
+        return Some(None);
     }
+    String::from_utf8(base64_decode(input)?).ok().map(Some)
+}
+
+fn decode_algorithm(input: &[u8]) -> Option<Algorithm> {
+    Algorithm::from_name(std::str::from_utf8(&base64_decode(input)?).ok()?)
 }
 
 /// Calculate perfect base64 encoded size
@@ -117,6 +319,7 @@ const fn div_ceil(lhs: usize, rhs: usize) -> usize {
 
 #[cfg(test)]
 pub mod tests {
+    pub use super::algorithm::tests::{get_test_ecdsa_key, get_test_rsa_key};
     use super::*;
 
     pub fn get_test_public_key() -> String {
@@ -132,10 +335,31 @@ pub mod tests {
         let sm1 = SignedMessage {
             message: "message".as_bytes().to_vec(),
             signature: "signature".as_bytes().to_vec(),
+            key_id: None,
+            algorithm: Algorithm::Ed25519,
+        };
+        let encoded = sm1.encode();
+        assert_eq!(encoded.len(), sm1.get_encoded_len());
+        let sm2 = SignedMessage::decode(encoded).unwrap();
+        assert_eq!(sm1.message, sm2.message);
+        assert_eq!(sm1.signature, sm2.signature);
+        assert_eq!(sm2.key_id, None);
+    }
+
+    #[test]
+    fn serialization_with_key_id() {
+        let sm1 = SignedMessage {
+            message: "message".as_bytes().to_vec(),
+            signature: "signature".as_bytes().to_vec(),
+            key_id: Some(String::from("2022-07")),
+            algorithm: Algorithm::Ed25519,
         };
-        let sm2 = SignedMessage::decode(&sm1.encode()).unwrap();
+        let encoded = sm1.encode();
+        assert_eq!(encoded.len(), sm1.get_encoded_len());
+        let sm2 = SignedMessage::decode(encoded).unwrap();
         assert_eq!(sm1.message, sm2.message);
         assert_eq!(sm1.signature, sm2.signature);
+        assert_eq!(sm2.key_id(), Some("2022-07"));
     }
 
     #[test]
@@ -143,17 +367,114 @@ pub mod tests {
         let message = "message".as_bytes().to_vec();
         let key = PrivateKey::from_base64(&get_test_private_key()).unwrap();
         let sm = SignedMessage::create(message, &key);
-        assert_eq!(sm.encode(), String::from("bWVzc2FnZQ.gH3fe9YO9tEv7f8adiZ2w7F6-7doNp3yyaDrfWuQNCuJi6bwF2jqm7v4p-wANdOahO1wvULOH96JJDnQlUoEDw"));
+        assert_eq!(sm.encode(), String::from("v1.bWVzc2FnZQ.gH3fe9YO9tEv7f8adiZ2w7F6-7doNp3yyaDrfWuQNCuJi6bwF2jqm7v4p-wANdOahO1wvULOH96JJDnQlUoEDw..RWREU0E"));
+        assert_eq!(sm.encode_legacy(), String::from("bWVzc2FnZQ.gH3fe9YO9tEv7f8adiZ2w7F6-7doNp3yyaDrfWuQNCuJi6bwF2jqm7v4p-wANdOahO1wvULOH96JJDnQlUoEDw"));
     }
 
     #[test]
     fn should_verify_previous_encoded() {
-        let sm = SignedMessage::decode("bWVzc2FnZQ.gH3fe9YO9tEv7f8adiZ2w7F6-7doNp3yyaDrfWuQNCuJi6bwF2jqm7v4p-wANdOahO1wvULOH96JJDnQlUoEDw")
-            .expect("valid encoded");
+        let encoded = "bWVzc2FnZQ.gH3fe9YO9tEv7f8adiZ2w7F6-7doNp3yyaDrfWuQNCuJi6bwF2jqm7v4p-wANdOahO1wvULOH96JJDnQlUoEDw";
+        assert!(SignedMessage::decode(encoded).is_none());
+        let sm = SignedMessage::decode_legacy(encoded).expect("valid encoded");
         let public_key = PublicKey::from_base64(&get_test_public_key()).unwrap();
         assert!(sm.verify(&public_key));
     }
 
+    #[test]
+    fn legacy_serialization() {
+        let key = get_test_ecdsa_key(Algorithm::EcdsaP256Sha256);
+        for sm1 in [
+            SignedMessage::create("message".as_bytes().to_vec(), &key),
+            SignedMessage::create_with_key_id("message".as_bytes().to_vec(), "ec".into(), &key),
+        ] {
+            let sm2 = SignedMessage::decode_legacy(sm1.encode_legacy()).unwrap();
+            assert_eq!(sm2.algorithm(), Algorithm::EcdsaP256Sha256);
+            assert_eq!(sm1.key_id(), sm2.key_id());
+            assert!(sm2.verify(&key.public_key()));
+        }
+    }
+
+    #[test]
+    fn decode_should_reject_extra_segments() {
+        let key = PrivateKey::from_base64(&get_test_private_key()).unwrap();
+        let sm =
+            SignedMessage::create_with_key_id("message".as_bytes().to_vec(), "k1".into(), &key);
+        let encoded = sm.encode();
+        assert!(SignedMessage::decode(&encoded).is_some());
+        assert!(SignedMessage::decode(format!("{encoded}.")).is_none());
+        assert!(SignedMessage::decode(format!("{encoded}.ZXh0cmE")).is_none());
+        assert!(SignedMessage::decode(encoded.replacen("v1", "v2", 1)).is_none());
+        assert!(SignedMessage::decode(&encoded[..encoded.rfind('.').unwrap()]).is_none());
+
+        let legacy = sm.encode_legacy();
+        assert!(SignedMessage::decode_legacy(&legacy).is_some());
+        assert!(SignedMessage::decode_legacy(format!("{legacy}.RWREU0E.ZXh0cmE")).is_none());
+        assert!(SignedMessage::decode_legacy(&encoded).is_none());
+    }
+
+    #[test]
+    fn base64_round_trip() {
+        let private_key = PrivateKey::from_base64(&get_test_private_key()).unwrap();
+        assert_eq!(private_key.to_base64(), get_test_private_key());
+        assert_eq!(private_key.public_key().to_base64(), get_test_public_key());
+    }
+
+    #[test]
+    fn generated_key_should_sign() {
+        let private_key = PrivateKey::generate().unwrap();
+        let other_key = PrivateKey::generate().unwrap();
+        assert_ne!(private_key.to_base64(), other_key.to_base64());
+
+        let sm = SignedMessage::create("message".as_bytes().to_vec(), &private_key);
+        let public_key = PublicKey::from_base64(&private_key.public_key().to_base64()).unwrap();
+        assert!(sm.verify(&public_key));
+        assert!(!sm.verify(&other_key.public_key()));
+    }
+
+    #[test]
+    fn serialization_with_algorithm() {
+        let key = get_test_ecdsa_key(Algorithm::EcdsaP256Sha256);
+        for sm1 in [
+            SignedMessage::create("message".as_bytes().to_vec(), &key),
+            SignedMessage::create_with_key_id("message".as_bytes().to_vec(), "ec".into(), &key),
+        ] {
+            let encoded = sm1.encode();
+            assert_eq!(encoded.len(), sm1.get_encoded_len());
+            let sm2 = SignedMessage::decode(encoded).unwrap();
+            assert_eq!(sm2.algorithm(), Algorithm::EcdsaP256Sha256);
+            assert_eq!(sm1.key_id(), sm2.key_id());
+            assert!(sm2.verify(&key.public_key()));
+        }
+    }
+
+    #[test]
+    fn verify_should_reject_algorithm_mismatch() {
+        let key = get_test_ecdsa_key(Algorithm::EcdsaP256Sha256);
+        let sm = SignedMessage::create("message".as_bytes().to_vec(), &key);
+        let public_key = key.public_key();
+        let mislabeled = VerifyingKey::new(Algorithm::EcdsaP384Sha384, public_key.as_bytes());
+        assert!(sm.verify(&public_key));
+        assert!(!sm.verify(&mislabeled));
+
+        let ed25519 = PrivateKey::from_base64(&get_test_private_key()).unwrap();
+        let sm = SignedMessage::create("message".as_bytes().to_vec(), &ed25519);
+        let mislabeled =
+            VerifyingKey::new(Algorithm::EcdsaP256Sha256, ed25519.public_key().as_bytes());
+        assert!(!sm.verify(&mislabeled));
+        assert!(sm.verify(&VerifyingKey::from(ed25519.public_key())));
+    }
+
+    #[test]
+    fn hmac_serialization() {
+        let key = HmacKey::generate(Algorithm::HmacSha256).unwrap();
+        let sm = SignedMessage::create("message".as_bytes().to_vec(), &key);
+        let sm = SignedMessage::decode(sm.encode()).unwrap();
+        assert_eq!(sm.algorithm(), Algorithm::HmacSha256);
+        assert!(sm.verify_hmac(&key));
+        assert!(!sm.verify_hmac(&HmacKey::generate(Algorithm::HmacSha256).unwrap()));
+        assert!(!sm.verify_hmac(&HmacKey::new(Algorithm::HmacSha512, &[0; 64]).unwrap()));
+    }
+
     #[test]
     fn verify_url_safe_no_pad_len() {
         let bytes: &[u8] = &[0, 0, 0, 0, 0, 0, 0, 0];
@@ -0,0 +1,330 @@
+use std::fmt::{self, Display};
+
+use ring::rand::SystemRandom;
+use ring::signature::{self, EcdsaKeyPair, KeyPair, RsaKeyPair, UnparsedPublicKey};
+
+use super::{PrivateKey, PublicKey};
+
+/// Signature algorithm, the name is carried in signed messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum Algorithm {
+    Ed25519,
+    EcdsaP256Sha256,
+    EcdsaP384Sha384,
+    RsaPkcs1Sha256,
+    RsaPssSha256,
+    HmacSha256,
+    HmacSha512,
+}
+
+impl Algorithm {
+    /// Name of the algorithm as registered for JSON Web Signature (RFC 7518, RFC 8037)
+    pub fn name(&self) -> &'static str {
+        use Algorithm::*;
+        match self {
+            Ed25519 => "EdDSA",
+            EcdsaP256Sha256 => "ES256",
+            EcdsaP384Sha384 => "ES384",
+            RsaPkcs1Sha256 => "RS256",
+            RsaPssSha256 => "PS256",
+            HmacSha256 => "HS256",
+            HmacSha512 => "HS512",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        use Algorithm::*;
+        [
+            Ed25519,
+            EcdsaP256Sha256,
+            EcdsaP384Sha384,
+            RsaPkcs1Sha256,
+            RsaPssSha256,
+            HmacSha256,
+            HmacSha512,
+        ]
+        .into_iter()
+        .find(|algorithm| algorithm.name() == name)
+    }
+
+    /// Whether the algorithm uses a shared secret instead of a key pair
+    pub fn is_symmetric(&self) -> bool {
+        matches!(self, Algorithm::HmacSha256 | Algorithm::HmacSha512)
+    }
+
+    fn verification_algorithm(&self) -> Option<&'static dyn signature::VerificationAlgorithm> {
+        use Algorithm::*;
+        Some(match self {
+            Ed25519 => &signature::ED25519,
+            EcdsaP256Sha256 => &signature::ECDSA_P256_SHA256_FIXED,
+            EcdsaP384Sha384 => &signature::ECDSA_P384_SHA384_FIXED,
+            RsaPkcs1Sha256 => &signature::RSA_PKCS1_2048_8192_SHA256,
+            RsaPssSha256 => &signature::RSA_PSS_2048_8192_SHA256,
+            HmacSha256 | HmacSha512 => return None,
+        })
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+pub trait Signer: Send + Sync {
+    fn algorithm(&self) -> Algorithm;
+
+    /// Sign the message
+    ///
+    /// Randomized algorithms panic if the system random number generator fails.
+    fn sign(&self, message: &[u8]) -> Vec<u8>;
+}
+
+pub trait Verifier: Send + Sync {
+    fn algorithm(&self) -> Algorithm;
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+impl Signer for PrivateKey {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Ed25519
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        PrivateKey::sign(self, message)
+    }
+}
+
+impl Verifier for PublicKey {
+    fn algorithm(&self) -> Algorithm {
+        Algorithm::Ed25519
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        PublicKey::verify(self, message, signature)
+    }
+}
+
+/// Public key of any supported asymmetric algorithm
+///
+/// Key bytes are the raw key for Ed25519, the uncompressed point for ECDSA and
+/// DER encoded `RSAPublicKey` for RSA.
+#[derive(Clone)]
+pub struct VerifyingKey {
+    algorithm: Algorithm,
+    bytes: Vec<u8>,
+}
+
+impl VerifyingKey {
+    pub fn new(algorithm: Algorithm, bytes: &[u8]) -> Self {
+        Self {
+            algorithm,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl Verifier for VerifyingKey {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        self.algorithm
+            .verification_algorithm()
+            .is_some_and(|algorithm| {
+                UnparsedPublicKey::new(algorithm, &self.bytes)
+                    .verify(message, signature)
+                    .is_ok()
+            })
+    }
+}
+
+impl From<PublicKey> for VerifyingKey {
+    fn from(key: PublicKey) -> Self {
+        Self::new(Algorithm::Ed25519, key.as_bytes())
+    }
+}
+
+pub struct EcdsaPrivateKey {
+    algorithm: Algorithm,
+    key_pair: EcdsaKeyPair,
+    rng: SystemRandom,
+}
+
+impl EcdsaPrivateKey {
+    /// Parse PKCS#8 DER document, `algorithm` must be one of ECDSA algorithms
+    pub fn from_pkcs8(algorithm: Algorithm, der: &[u8]) -> Option<Self> {
+        let signing_algorithm = match algorithm {
+            Algorithm::EcdsaP256Sha256 => &signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            Algorithm::EcdsaP384Sha384 => &signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+            _ => return None,
+        };
+        let key_pair = EcdsaKeyPair::from_pkcs8(signing_algorithm, der).ok()?;
+        Some(Self {
+            algorithm,
+            key_pair,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    pub fn public_key(&self) -> VerifyingKey {
+        VerifyingKey::new(self.algorithm, self.key_pair.public_key().as_ref())
+    }
+}
+
+impl Signer for EcdsaPrivateKey {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.key_pair
+            .sign(&self.rng, message)
+            .expect("Fail to generate ECDSA nonce")
+            .as_ref()
+            .to_vec()
+    }
+}
+
+pub struct RsaPrivateKey {
+    algorithm: Algorithm,
+    key_pair: RsaKeyPair,
+    rng: SystemRandom,
+}
+
+impl RsaPrivateKey {
+    /// Parse PKCS#8 DER document, `algorithm` must be one of RSA algorithms
+    pub fn from_pkcs8(algorithm: Algorithm, der: &[u8]) -> Option<Self> {
+        RsaKeyPair::from_pkcs8(der)
+            .ok()
+            .and_then(|key_pair| Self::new(algorithm, key_pair))
+    }
+
+    /// Parse DER encoded `RSAPrivateKey` (PKCS#1)
+    pub fn from_der(algorithm: Algorithm, der: &[u8]) -> Option<Self> {
+        RsaKeyPair::from_der(der)
+            .ok()
+            .and_then(|key_pair| Self::new(algorithm, key_pair))
+    }
+
+    fn new(algorithm: Algorithm, key_pair: RsaKeyPair) -> Option<Self> {
+        matches!(
+            algorithm,
+            Algorithm::RsaPkcs1Sha256 | Algorithm::RsaPssSha256
+        )
+        .then(|| Self {
+            algorithm,
+            key_pair,
+            rng: SystemRandom::new(),
+        })
+    }
+
+    pub fn public_key(&self) -> VerifyingKey {
+        VerifyingKey::new(self.algorithm, self.key_pair.public_key().as_ref())
+    }
+}
+
+impl Signer for RsaPrivateKey {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        let padding: &'static dyn signature::RsaEncoding = match self.algorithm {
+            Algorithm::RsaPssSha256 => &signature::RSA_PSS_SHA256,
+            _ => &signature::RSA_PKCS1_SHA256,
+        };
+        let mut signature = vec![0; self.key_pair.public_modulus_len()];
+        self.key_pair
+            .sign(padding, &self.rng, message, &mut signature)
+            .expect("Fail to sign with RSA key");
+        signature
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use ring::signature::{ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING};
+
+    use crate::crypto::key_format::pem_decode;
+    use crate::crypto::tests::get_test_private_key;
+
+    use super::*;
+
+    /// Generated by `openssl genpkey -algorithm RSA -pkeyopt rsa_keygen_bits:2048`
+    const TEST_RSA_PRIVATE_KEY: &str = include_str!("test_rsa.pem");
+
+    pub fn get_test_ecdsa_key(algorithm: Algorithm) -> EcdsaPrivateKey {
+        let signing_algorithm = match algorithm {
+            Algorithm::EcdsaP256Sha256 => &ECDSA_P256_SHA256_FIXED_SIGNING,
+            _ => &ECDSA_P384_SHA384_FIXED_SIGNING,
+        };
+        let document =
+            EcdsaKeyPair::generate_pkcs8(signing_algorithm, &SystemRandom::new()).unwrap();
+        EcdsaPrivateKey::from_pkcs8(algorithm, document.as_ref()).unwrap()
+    }
+
+    pub fn get_test_rsa_key(algorithm: Algorithm) -> RsaPrivateKey {
+        let der = pem_decode(TEST_RSA_PRIVATE_KEY, "PRIVATE KEY").unwrap();
+        RsaPrivateKey::from_pkcs8(algorithm, &der).unwrap()
+    }
+
+    fn assert_sign_and_verify(signer: &dyn Signer, verifier: &dyn Verifier) {
+        let message = b"message";
+        let signature = signer.sign(message);
+        assert_eq!(signer.algorithm(), verifier.algorithm());
+        assert!(verifier.verify(message, &signature));
+        assert!(!verifier.verify(b"other message", &signature));
+    }
+
+    #[test]
+    fn algorithm_names() {
+        for algorithm in [
+            Algorithm::Ed25519,
+            Algorithm::EcdsaP256Sha256,
+            Algorithm::EcdsaP384Sha384,
+            Algorithm::RsaPkcs1Sha256,
+            Algorithm::RsaPssSha256,
+            Algorithm::HmacSha256,
+            Algorithm::HmacSha512,
+        ] {
+            assert_eq!(Algorithm::from_name(algorithm.name()), Some(algorithm));
+        }
+        assert_eq!(Algorithm::from_name("none"), None);
+    }
+
+    #[test]
+    fn ed25519() {
+        let key = PrivateKey::from_base64(&get_test_private_key()).unwrap();
+        assert_sign_and_verify(&key, &key.public_key());
+        assert_sign_and_verify(&key, &VerifyingKey::from(key.public_key()));
+    }
+
+    #[test]
+    fn ecdsa() {
+        for algorithm in [Algorithm::EcdsaP256Sha256, Algorithm::EcdsaP384Sha384] {
+            let key = get_test_ecdsa_key(algorithm);
+            assert_sign_and_verify(&key, &key.public_key());
+        }
+        let key = get_test_ecdsa_key(Algorithm::EcdsaP256Sha256);
+        let verifier = VerifyingKey::new(Algorithm::EcdsaP384Sha384, key.public_key().as_bytes());
+        assert!(!verifier.verify(b"message", &key.sign(b"message")));
+    }
+
+    #[test]
+    fn rsa() {
+        for algorithm in [Algorithm::RsaPkcs1Sha256, Algorithm::RsaPssSha256] {
+            let key = get_test_rsa_key(algorithm);
+            assert_sign_and_verify(&key, &key.public_key());
+        }
+        let der = pem_decode(TEST_RSA_PRIVATE_KEY, "PRIVATE KEY").unwrap();
+        assert!(RsaPrivateKey::from_pkcs8(Algorithm::Ed25519, &der).is_none());
+    }
+}
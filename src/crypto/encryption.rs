@@ -0,0 +1,161 @@
+use ring::aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+const KEY_LEN: usize = 32;
+
+/// AES-256-GCM key for encrypted tokens
+///
+/// Nonces are random, rotate the key well before it has encrypted 2^32 tokens.
+pub struct EncryptionKey {
+    key: LessSafeKey,
+    secret: [u8; KEY_LEN],
+}
+
+impl EncryptionKey {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let secret = bytes.try_into().ok()?;
+        let key = UnboundKey::new(&aead::AES_256_GCM, bytes).ok()?;
+        Some(Self {
+            key: LessSafeKey::new(key),
+            secret,
+        })
+    }
+
+    /// Generate a new key from the operating system's secure random number generator
+    pub fn generate() -> Option<Self> {
+        let mut secret = [0u8; KEY_LEN];
+        SystemRandom::new().fill(&mut secret).ok()?;
+        Self::from_bytes(&secret)
+    }
+
+    pub fn from_base64<T: ?Sized + AsRef<[u8]>>(input: &T) -> Option<Self> {
+        base64::decode_config(input, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|secret| Self::from_bytes(&secret))
+    }
+
+    /// Encode key in the format accepted by [`from_base64`](Self::from_base64)
+    pub fn to_base64(&self) -> String {
+        base64::encode_config(self.secret, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Encrypt to `nonce || ciphertext || tag`, return `None` if nonce generation fails
+    fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new().fill(&mut nonce).ok()?;
+        let mut output = nonce.to_vec();
+        let mut in_out = plaintext.to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(aad),
+                &mut in_out,
+            )
+            .ok()?;
+        output.append(&mut in_out);
+        Some(output)
+    }
+
+    fn open(&self, sealed: &[u8], aad: &[u8]) -> Option<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return None;
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce).ok()?;
+        let mut in_out = ciphertext.to_vec();
+        let plaintext_len = self
+            .key
+            .open_in_place(nonce, Aad::from(aad), &mut in_out)
+            .ok()?
+            .len();
+        in_out.truncate(plaintext_len);
+        Some(in_out)
+    }
+}
+
+const SEPARATOR: char = '.';
+
+/// Message encrypted with a key identified by `key_id`, the key id is authenticated as well
+pub struct EncryptedMessage {
+    key_id: String,
+    sealed: Vec<u8>,
+}
+
+impl EncryptedMessage {
+    /// Return `None` if the random number generator fails
+    pub fn seal(plaintext: &[u8], key_id: String, key: &EncryptionKey) -> Option<Self> {
+        let sealed = key.seal(plaintext, key_id.as_bytes())?;
+        Some(Self { key_id, sealed })
+    }
+
+    /// Decrypt and check integrity of the message
+    pub fn open(&self, key: &EncryptionKey) -> Option<Vec<u8>> {
+        key.open(&self.sealed, self.key_id.as_bytes())
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Encode to `base64(key_id).base64(nonce || ciphertext || tag)`
+    pub fn encode(&self) -> String {
+        let mut output = base64::encode_config(&self.key_id, base64::URL_SAFE_NO_PAD);
+        output.push(SEPARATOR);
+        base64::encode_config_buf(&self.sealed, base64::URL_SAFE_NO_PAD, &mut output);
+        output
+    }
+
+    pub fn decode<T: AsRef<[u8]>>(input: T) -> Option<Self> {
+        let input = std::str::from_utf8(input.as_ref()).ok()?;
+        let (key_id, sealed) = input.split_once(SEPARATOR)?;
+        let decode = |input| base64::decode_config(input, base64::URL_SAFE_NO_PAD).ok();
+        Some(Self {
+            key_id: String::from_utf8(decode(key_id)?).ok()?,
+            sealed: decode(sealed)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_and_open() {
+        let key = EncryptionKey::generate().unwrap();
+        let message = EncryptedMessage::seal(b"message", "e1".into(), &key).unwrap();
+        let encoded = message.encode();
+        assert!(!encoded.contains("bWVzc2FnZQ"));
+
+        let message = EncryptedMessage::decode(&encoded).unwrap();
+        assert_eq!(message.key_id(), "e1");
+        assert_eq!(message.open(&key).unwrap(), b"message");
+        assert!(message.open(&EncryptionKey::generate().unwrap()).is_none());
+
+        let key = EncryptionKey::from_base64(&key.to_base64()).unwrap();
+        assert_eq!(message.open(&key).unwrap(), b"message");
+    }
+
+    #[test]
+    fn key_id_is_authenticated() {
+        let key = EncryptionKey::generate().unwrap();
+        let message = EncryptedMessage::seal(b"message", "e1".into(), &key).unwrap();
+        let encoded = message.encode();
+        let (_, sealed) = encoded.split_once(SEPARATOR).unwrap();
+        let forged = format!(
+            "{}.{sealed}",
+            base64::encode_config("e2", base64::URL_SAFE_NO_PAD)
+        );
+        let forged = EncryptedMessage::decode(forged).unwrap();
+        assert!(forged.open(&key).is_none());
+    }
+
+    #[test]
+    fn reject_malformed() {
+        assert!(EncryptionKey::from_bytes(&[0; 16]).is_none());
+        assert!(EncryptedMessage::decode("ZTE").is_none());
+        let key = EncryptionKey::generate().unwrap();
+        let message = EncryptedMessage::decode("ZTE.AAAA").unwrap();
+        assert!(message.open(&key).is_none());
+    }
+}
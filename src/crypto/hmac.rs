@@ -0,0 +1,101 @@
+use ring::hmac;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use super::{Algorithm, Signer};
+
+/// Secret shared between services for HMAC signed tokens
+///
+/// It is deliberately not a [`Verifier`](super::Verifier), so it can never be put into a
+/// [`KeyRing`](crate::token::KeyRing) of public keys. Only
+/// [`HmacTokenValidator`](crate::token::HmacTokenValidator) verifies HMAC tokens.
+pub struct HmacKey {
+    algorithm: Algorithm,
+    key: hmac::Key,
+    secret: Vec<u8>,
+}
+
+impl HmacKey {
+    /// Create key from secret bytes
+    ///
+    /// Return `None` if `algorithm` is not an HMAC algorithm or the secret is shorter than
+    /// the hash output, as required by RFC 7518.
+    pub fn new(algorithm: Algorithm, secret: &[u8]) -> Option<Self> {
+        let (hmac_algorithm, min_len) = match algorithm {
+            Algorithm::HmacSha256 => (hmac::HMAC_SHA256, 32),
+            Algorithm::HmacSha512 => (hmac::HMAC_SHA512, 64),
+            _ => return None,
+        };
+        if secret.len() < min_len {
+            return None;
+        }
+        Some(Self {
+            algorithm,
+            key: hmac::Key::new(hmac_algorithm, secret),
+            secret: secret.to_vec(),
+        })
+    }
+
+    /// Generate a new secret from the operating system's secure random number generator
+    pub fn generate(algorithm: Algorithm) -> Option<Self> {
+        let mut secret = [0u8; 64];
+        SystemRandom::new().fill(&mut secret).ok()?;
+        Self::new(algorithm, &secret)
+    }
+
+    pub fn from_base64<T: ?Sized + AsRef<[u8]>>(algorithm: Algorithm, input: &T) -> Option<Self> {
+        base64::decode_config(input, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|secret| Self::new(algorithm, &secret))
+    }
+
+    /// Encode secret in the format accepted by [`from_base64`](Self::from_base64)
+    pub fn to_base64(&self) -> String {
+        base64::encode_config(&self.secret, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    /// Compare tag of the message in constant time
+    pub fn verify(&self, message: &[u8], tag: &[u8]) -> bool {
+        hmac::verify(&self.key, message, tag).is_ok()
+    }
+}
+
+impl Signer for HmacKey {
+    fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    fn sign(&self, message: &[u8]) -> Vec<u8> {
+        hmac::sign(&self.key, message).as_ref().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        for algorithm in [Algorithm::HmacSha256, Algorithm::HmacSha512] {
+            let key = HmacKey::generate(algorithm).unwrap();
+            let tag = key.sign(b"message");
+            assert!(key.verify(b"message", &tag));
+            assert!(!key.verify(b"other message", &tag));
+            assert!(!key.verify(b"message", &tag[1..]));
+
+            let other_key = HmacKey::from_base64(algorithm, &key.to_base64()).unwrap();
+            assert!(other_key.verify(b"message", &tag));
+        }
+    }
+
+    #[test]
+    fn reject_weak_secret() {
+        assert!(HmacKey::new(Algorithm::HmacSha256, &[0; 31]).is_none());
+        assert!(HmacKey::new(Algorithm::HmacSha256, &[0; 32]).is_some());
+        assert!(HmacKey::new(Algorithm::HmacSha512, &[0; 32]).is_none());
+        assert!(HmacKey::new(Algorithm::Ed25519, &[0; 64]).is_none());
+    }
+}
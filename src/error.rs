@@ -3,22 +3,54 @@ use std::fmt::{self, Debug, Display};
 #[derive(Debug, Clone, Copy)]
 #[non_exhaustive]
 pub enum Error {
+    DecryptionFail,
+    DisallowedAlgorithm,
     ExpiredAccessToken,
     InvalidAccessToken,
+    InvalidAttenuation,
+    InvalidAudience,
+    InvalidIssuer,
     InvalidSignedMessage,
+    MissingAccessToken,
+    NotYetValidAccessToken,
+    RevokedAccessToken,
     SignatureVerificationFail,
     Unauthorized,
+    UnknownEncryptionKey,
+    UnknownPermission,
+    UnknownSigningKey,
+}
+
+impl Error {
+    /// Whether the access token is valid but not allowed to do the operation
+    ///
+    /// Integrations respond to these errors with 403 or `PERMISSION_DENIED`, and to every other
+    /// one with 401 or `UNAUTHENTICATED`.
+    pub fn is_forbidden(&self) -> bool {
+        matches!(self, Error::Unauthorized)
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use Error::*;
         match *self {
+            DecryptionFail => f.write_str("decryption fail"),
+            DisallowedAlgorithm => f.write_str("disallowed signature algorithm"),
             ExpiredAccessToken => f.write_str("expired access token"),
             InvalidAccessToken => f.write_str("invalid access token"),
+            InvalidAttenuation => f.write_str("derived token exceeds its parent"),
+            InvalidAudience => f.write_str("invalid audience"),
+            InvalidIssuer => f.write_str("invalid issuer"),
             InvalidSignedMessage => f.write_str("invalid signed message"),
+            MissingAccessToken => f.write_str("missing access token"),
+            NotYetValidAccessToken => f.write_str("access token is not yet valid"),
+            RevokedAccessToken => f.write_str("revoked access token"),
             SignatureVerificationFail => f.write_str("signature verification fail"),
             Unauthorized => f.write_str("unauthorized"),
+            UnknownEncryptionKey => f.write_str("unknown encryption key"),
+            UnknownPermission => f.write_str("unknown permission"),
+            UnknownSigningKey => f.write_str("unknown signing key"),
         }
     }
 }
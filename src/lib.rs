@@ -2,10 +2,24 @@
 
 pub use error::Error;
 
+#[cfg(feature = "actix-web")]
+pub mod actix_web;
+pub mod audit;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(any(feature = "actix-web", feature = "axum", feature = "tonic"))]
+mod bearer;
 pub mod crypto;
 mod error;
 pub mod rbac;
 pub mod token;
+#[cfg(feature = "tonic")]
+pub mod tonic;
 
 #[cfg(test)]
 mod protos;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use num_traits;
+}
@@ -1,4 +1,4 @@
-// This file is generated by rust-protobuf 3.0.3. Do not edit
+// This file is generated by rust-protobuf 3.7.2. Do not edit
 // .proto file is parsed by pure
 // @generated
 
@@ -9,7 +9,6 @@
 #![allow(unused_attributes)]
 #![cfg_attr(rustfmt, rustfmt::skip)]
 
-#![allow(box_pointers)]
 #![allow(dead_code)]
 #![allow(missing_docs)]
 #![allow(non_camel_case_types)]
@@ -23,16 +22,30 @@
 
 /// Generated files are compatible only with the same version
 /// of protobuf runtime.
-const _PROTOBUF_VERSION_CHECK: () = ::protobuf::VERSION_3_0_3;
+const _PROTOBUF_VERSION_CHECK: () = ::protobuf::VERSION_3_7_2;
 
-#[derive(PartialEq,Clone,Default,Debug)]
 // @@protoc_insertion_point(message:TestAccessToken)
+#[derive(PartialEq,Clone,Default,Debug)]
 pub struct TestAccessToken {
     // message fields
     // @@protoc_insertion_point(field:TestAccessToken.expired)
     pub expired: bool,
     // @@protoc_insertion_point(field:TestAccessToken.permissions)
     pub permissions: ::std::vec::Vec<u8>,
+    // @@protoc_insertion_point(field:TestAccessToken.issued_at)
+    pub issued_at: u64,
+    // @@protoc_insertion_point(field:TestAccessToken.expires_at)
+    pub expires_at: u64,
+    // @@protoc_insertion_point(field:TestAccessToken.not_before)
+    pub not_before: u64,
+    // @@protoc_insertion_point(field:TestAccessToken.issuer)
+    pub issuer: ::std::string::String,
+    // @@protoc_insertion_point(field:TestAccessToken.audience)
+    pub audience: ::std::vec::Vec<::std::string::String>,
+    // @@protoc_insertion_point(field:TestAccessToken.subject)
+    pub subject: ::std::string::String,
+    // @@protoc_insertion_point(field:TestAccessToken.token_id)
+    pub token_id: ::std::string::String,
     // special fields
     // @@protoc_insertion_point(special_field:TestAccessToken.special_fields)
     pub special_fields: ::protobuf::SpecialFields,
@@ -50,7 +63,7 @@ impl TestAccessToken {
     }
 
     fn generated_message_descriptor_data() -> ::protobuf::reflect::GeneratedMessageDescriptorData {
-        let mut fields = ::std::vec::Vec::with_capacity(2);
+        let mut fields = ::std::vec::Vec::with_capacity(9);
         let mut oneofs = ::std::vec::Vec::with_capacity(0);
         fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
             "expired",
@@ -62,6 +75,41 @@ impl TestAccessToken {
             |m: &TestAccessToken| { &m.permissions },
             |m: &mut TestAccessToken| { &mut m.permissions },
         ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "issued_at",
+            |m: &TestAccessToken| { &m.issued_at },
+            |m: &mut TestAccessToken| { &mut m.issued_at },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "expires_at",
+            |m: &TestAccessToken| { &m.expires_at },
+            |m: &mut TestAccessToken| { &mut m.expires_at },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "not_before",
+            |m: &TestAccessToken| { &m.not_before },
+            |m: &mut TestAccessToken| { &mut m.not_before },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "issuer",
+            |m: &TestAccessToken| { &m.issuer },
+            |m: &mut TestAccessToken| { &mut m.issuer },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_vec_simpler_accessor::<_, _>(
+            "audience",
+            |m: &TestAccessToken| { &m.audience },
+            |m: &mut TestAccessToken| { &mut m.audience },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "subject",
+            |m: &TestAccessToken| { &m.subject },
+            |m: &mut TestAccessToken| { &mut m.subject },
+        ));
+        fields.push(::protobuf::reflect::rt::v2::make_simpler_field_accessor::<_, _>(
+            "token_id",
+            |m: &TestAccessToken| { &m.token_id },
+            |m: &mut TestAccessToken| { &mut m.token_id },
+        ));
         ::protobuf::reflect::GeneratedMessageDescriptorData::new_2::<TestAccessToken>(
             "TestAccessToken",
             fields,
@@ -86,6 +134,27 @@ impl ::protobuf::Message for TestAccessToken {
                 18 => {
                     self.permissions = is.read_bytes()?;
                 },
+                24 => {
+                    self.issued_at = is.read_uint64()?;
+                },
+                32 => {
+                    self.expires_at = is.read_uint64()?;
+                },
+                40 => {
+                    self.not_before = is.read_uint64()?;
+                },
+                50 => {
+                    self.issuer = is.read_string()?;
+                },
+                58 => {
+                    self.audience.push(is.read_string()?);
+                },
+                66 => {
+                    self.subject = is.read_string()?;
+                },
+                74 => {
+                    self.token_id = is.read_string()?;
+                },
                 tag => {
                     ::protobuf::rt::read_unknown_or_skip_group(tag, is, self.special_fields.mut_unknown_fields())?;
                 },
@@ -104,6 +173,27 @@ impl ::protobuf::Message for TestAccessToken {
         if !self.permissions.is_empty() {
             my_size += ::protobuf::rt::bytes_size(2, &self.permissions);
         }
+        if self.issued_at != 0 {
+            my_size += ::protobuf::rt::uint64_size(3, self.issued_at);
+        }
+        if self.expires_at != 0 {
+            my_size += ::protobuf::rt::uint64_size(4, self.expires_at);
+        }
+        if self.not_before != 0 {
+            my_size += ::protobuf::rt::uint64_size(5, self.not_before);
+        }
+        if !self.issuer.is_empty() {
+            my_size += ::protobuf::rt::string_size(6, &self.issuer);
+        }
+        for value in &self.audience {
+            my_size += ::protobuf::rt::string_size(7, &value);
+        };
+        if !self.subject.is_empty() {
+            my_size += ::protobuf::rt::string_size(8, &self.subject);
+        }
+        if !self.token_id.is_empty() {
+            my_size += ::protobuf::rt::string_size(9, &self.token_id);
+        }
         my_size += ::protobuf::rt::unknown_fields_size(self.special_fields.unknown_fields());
         self.special_fields.cached_size().set(my_size as u32);
         my_size
@@ -116,6 +206,27 @@ impl ::protobuf::Message for TestAccessToken {
         if !self.permissions.is_empty() {
             os.write_bytes(2, &self.permissions)?;
         }
+        if self.issued_at != 0 {
+            os.write_uint64(3, self.issued_at)?;
+        }
+        if self.expires_at != 0 {
+            os.write_uint64(4, self.expires_at)?;
+        }
+        if self.not_before != 0 {
+            os.write_uint64(5, self.not_before)?;
+        }
+        if !self.issuer.is_empty() {
+            os.write_string(6, &self.issuer)?;
+        }
+        for v in &self.audience {
+            os.write_string(7, &v)?;
+        };
+        if !self.subject.is_empty() {
+            os.write_string(8, &self.subject)?;
+        }
+        if !self.token_id.is_empty() {
+            os.write_string(9, &self.token_id)?;
+        }
         os.write_unknown_fields(self.special_fields.unknown_fields())?;
         ::std::result::Result::Ok(())
     }
@@ -135,6 +246,13 @@ impl ::protobuf::Message for TestAccessToken {
     fn clear(&mut self) {
         self.expired = false;
         self.permissions.clear();
+        self.issued_at = 0;
+        self.expires_at = 0;
+        self.not_before = 0;
+        self.issuer.clear();
+        self.audience.clear();
+        self.subject.clear();
+        self.token_id.clear();
         self.special_fields.clear();
     }
 
@@ -142,6 +260,13 @@ impl ::protobuf::Message for TestAccessToken {
         static instance: TestAccessToken = TestAccessToken {
             expired: false,
             permissions: ::std::vec::Vec::new(),
+            issued_at: 0,
+            expires_at: 0,
+            not_before: 0,
+            issuer: ::std::string::String::new(),
+            audience: ::std::vec::Vec::new(),
+            subject: ::std::string::String::new(),
+            token_id: ::std::string::String::new(),
             special_fields: ::protobuf::SpecialFields::new(),
         };
         &instance
@@ -166,9 +291,14 @@ impl ::protobuf::reflect::ProtobufValue for TestAccessToken {
 }
 
 static file_descriptor_proto_data: &'static [u8] = b"\
-    \n\x0btoken.proto\"M\n\x0fTestAccessToken\x12\x18\n\x07expired\x18\x01\
-    \x20\x01(\x08R\x07expired\x12\x20\n\x0bpermissions\x18\x02\x20\x01(\x0cR\
-    \x0bpermissionsb\x06proto3\
+    \n\x0btoken.proto\"\x91\x02\n\x0fTestAccessToken\x12\x18\n\x07expired\
+    \x18\x01\x20\x01(\x08R\x07expired\x12\x20\n\x0bpermissions\x18\x02\x20\
+    \x01(\x0cR\x0bpermissions\x12\x1b\n\tissued_at\x18\x03\x20\x01(\x04R\x08\
+    issuedAt\x12\x1d\n\nexpires_at\x18\x04\x20\x01(\x04R\texpiresAt\x12\x1d\
+    \n\nnot_before\x18\x05\x20\x01(\x04R\tnotBefore\x12\x16\n\x06issuer\x18\
+    \x06\x20\x01(\tR\x06issuer\x12\x1a\n\x08audience\x18\x07\x20\x03(\tR\x08\
+    audience\x12\x18\n\x07subject\x18\x08\x20\x01(\tR\x07subject\x12\x19\n\
+    \x08token_id\x18\t\x20\x01(\tR\x07tokenIdb\x06proto3\
 ";
 
 /// `FileDescriptorProto` object which was a source for this generated file
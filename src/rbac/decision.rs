@@ -0,0 +1,261 @@
+use std::fmt::{self, Display};
+
+use serde_json::{json, Value};
+
+use crate::rbac::{Permission, Predicate};
+
+/// Outcome of [`Predicate::evaluate`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision<P: Permission> {
+    Granted,
+    Denied(Denial<P>),
+}
+
+/// Explanation of a denied decision, the failing sub-tree of the predicate
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Denial<P: Permission> {
+    /// `Contains` or `All` without these permissions
+    Missing(Box<[P]>),
+    /// `Any` without a single permission, each of them would have granted access
+    AnyOf(Box<[P]>),
+    /// `And` with more than one unsatisfied sub-predicate
+    ///
+    /// A single unsatisfied sub-predicate is reported on its own.
+    AllOf(Box<[Denial<P>]>),
+    /// `Or`, why each alternative was denied
+    OneOf(Box<[Denial<P>]>),
+    /// `Not` whose predicate is satisfied
+    Forbidden(Predicate<P>),
+}
+
+impl<P: Permission> Decision<P> {
+    pub fn is_granted(&self) -> bool {
+        matches!(self, Decision::Granted)
+    }
+
+    pub fn denial(&self) -> Option<&Denial<P>> {
+        match self {
+            Decision::Granted => None,
+            Decision::Denied(denial) => Some(denial),
+        }
+    }
+}
+
+impl<P: Permission + Display> Decision<P> {
+    /// Structured form, e.g. `{"granted":false,"denial":{"missing":["Read"]}}`
+    pub fn to_json(&self) -> Value {
+        match self {
+            Decision::Granted => json!({ "granted": true }),
+            Decision::Denied(denial) => json!({ "granted": false, "denial": denial.to_json() }),
+        }
+    }
+}
+
+impl<P: Permission> Denial<P> {
+    /// Structured form with permissions written by discriminant, e.g. `{"missing":[1]}`
+    pub(crate) fn to_discriminant_json(&self) -> Value {
+        self.to_json_with(&|permission| permission.to_u64().into(), &|predicate| {
+            predicate.to_discriminant_string()
+        })
+    }
+
+    fn to_json_with(
+        &self,
+        permission: &dyn Fn(&P) -> Value,
+        predicate: &dyn Fn(&Predicate<P>) -> String,
+    ) -> Value {
+        let permissions = |slice: &[P]| -> Value { slice.iter().map(permission).collect() };
+        let denials = |slice: &[Self]| -> Value {
+            slice
+                .iter()
+                .map(|denial| denial.to_json_with(permission, predicate))
+                .collect()
+        };
+        match self {
+            Denial::Missing(slice) => json!({ "missing": permissions(slice) }),
+            Denial::AnyOf(slice) => json!({ "any_of": permissions(slice) }),
+            Denial::AllOf(slice) => json!({ "all_of": denials(slice) }),
+            Denial::OneOf(slice) => json!({ "one_of": denials(slice) }),
+            Denial::Forbidden(forbidden) => json!({ "forbidden": predicate(forbidden) }),
+        }
+    }
+}
+
+impl<P: Permission + Display> Denial<P> {
+    /// Structured form, an object with a single key named after the variant
+    pub fn to_json(&self) -> Value {
+        self.to_json_with(&|permission| permission.to_string().into(), &|predicate| {
+            predicate.to_string()
+        })
+    }
+
+    fn fmt_indented(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        let (header, denials) = match self {
+            Denial::Missing(slice) => return fmt_permissions(f, "missing ", slice),
+            Denial::AnyOf(slice) => return fmt_permissions(f, "requires any of ", slice),
+            Denial::Forbidden(predicate) => return write!(f, "forbidden by {}", predicate),
+            Denial::AllOf(denials) => ("requires all of:", denials),
+            Denial::OneOf(denials) => ("requires one of:", denials),
+        };
+        f.write_str(header)?;
+        for denial in denials.iter() {
+            write!(f, "\n{:indent$}- ", "", indent = depth * 2)?;
+            denial.fmt_indented(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_permissions<P: Display>(
+    f: &mut fmt::Formatter<'_>,
+    prefix: &str,
+    slice: &[P],
+) -> fmt::Result {
+    f.write_str(prefix)?;
+    if slice.is_empty() {
+        return f.write_str("nothing");
+    }
+    for (index, permission) in slice.iter().enumerate() {
+        if index > 0 {
+            f.write_str(", ")?;
+        }
+        Display::fmt(permission, f)?;
+    }
+    Ok(())
+}
+
+/// Multi-line text, nested denials are listed as indented items
+impl<P: Permission + Display> Display for Denial<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+impl<P: Permission + Display> Display for Decision<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::Granted => f.write_str("granted"),
+            Decision::Denied(denial) => write!(f, "denied: {}", denial),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rbac::test_helpers::TestPermission::{self, *};
+    use crate::rbac::PermissionSet;
+
+    use super::*;
+
+    type TestPredicate = Predicate<TestPermission>;
+
+    fn deny(predicate: &TestPredicate, permissions: &[TestPermission]) -> Denial<TestPermission> {
+        let permissions: PermissionSet<_> = permissions.iter().copied().collect();
+        match predicate.evaluate(&permissions) {
+            Decision::Denied(denial) => denial,
+            Decision::Granted => panic!("{} should be denied", predicate),
+        }
+    }
+
+    #[test]
+    fn granted_should_agree_with_satisfy() {
+        let predicates = [
+            TestPredicate::Nil,
+            TestPredicate::all([Permission1, Permission2]) | Permission3,
+            TestPredicate::contains(Permission1) & !TestPredicate::contains(Permission4),
+            TestPredicate::any([Permission2, Permission4]),
+        ];
+        let sets: [&[TestPermission]; 4] = [
+            &[],
+            &[Permission1, Permission2],
+            &[Permission3, Permission4],
+            &[Permission1, Permission4],
+        ];
+        for predicate in &predicates {
+            for set in sets {
+                let set: PermissionSet<_> = set.iter().copied().collect();
+                assert_eq!(
+                    predicate.evaluate(&set).is_granted(),
+                    predicate.satisfy(&set)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn all_should_list_missing_permissions() {
+        let predicate = TestPredicate::all([Permission1, Permission2, Permission3]);
+        let denial = deny(&predicate, &[Permission2]);
+        assert_eq!(
+            denial,
+            Denial::Missing(Box::new([Permission1, Permission3]))
+        );
+        assert_eq!(denial.to_string(), "missing Permission1, Permission3");
+        assert_eq!(
+            denial.to_json(),
+            json!({ "missing": ["Permission1", "Permission3"] })
+        );
+    }
+
+    #[test]
+    fn any_should_list_alternatives() {
+        let predicate = TestPredicate::any([Permission1, Permission2]);
+        let denial = deny(&predicate, &[Permission3]);
+        assert_eq!(denial, Denial::AnyOf(Box::new([Permission1, Permission2])));
+        assert_eq!(
+            denial.to_string(),
+            "requires any of Permission1, Permission2"
+        );
+    }
+
+    #[test]
+    fn nested_predicate_should_report_failing_sub_tree() {
+        // Permission1 & (Permission2 & Permission3 | Permission4) & !Permission5
+        let predicate = TestPredicate::contains(Permission1)
+            & (TestPredicate::all([Permission2, Permission3]) | Permission4)
+            & !TestPredicate::contains(Permission5);
+
+        let denial = deny(&predicate, &[Permission1, Permission2]);
+        assert_eq!(
+            denial,
+            Denial::OneOf(Box::new([
+                Denial::Missing(Box::new([Permission3])),
+                Denial::Missing(Box::new([Permission4])),
+            ]))
+        );
+
+        let denial = deny(&predicate, &[Permission2, Permission5]);
+        assert_eq!(
+            denial.to_string(),
+            "requires all of:\n\
+             - missing Permission1\n\
+             - requires one of:\n  \
+               - missing Permission3\n  \
+               - missing Permission4\n\
+             - forbidden by Permission5"
+        );
+        assert_eq!(
+            denial.to_discriminant_json(),
+            json!({
+                "all_of": [
+                    { "missing": [1] },
+                    { "one_of": [{ "missing": [3] }, { "missing": [4] }] },
+                    { "forbidden": "5" },
+                ]
+            })
+        );
+        assert_eq!(
+            Decision::Denied(denial).to_json(),
+            json!({
+                "granted": false,
+                "denial": {
+                    "all_of": [
+                        { "missing": ["Permission1"] },
+                        { "one_of": [{ "missing": ["Permission3"] }, { "missing": ["Permission4"] }] },
+                        { "forbidden": "Permission5" },
+                    ]
+                }
+            })
+        );
+    }
+}
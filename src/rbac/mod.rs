@@ -1,12 +1,18 @@
+pub use decision::{Decision, Denial};
+pub use parser::{PredicateParseError, UnknownPermission};
 pub use permission_set::PermissionSet;
 pub use predicate::Predicate;
-pub use role_set::RoleSet;
-pub use traits::{Permission, Role};
+pub use role_set::{RoleCycleError, RoleSet};
+pub use traits::{Permission, Requirement, Role};
+#[cfg(feature = "derive")]
+pub use tokidator_derive::{Permission, Role};
 pub use utils::json_discriminant_array_to_vec;
 
 #[cfg(test)]
 pub(crate) mod test_helpers;
 
+mod decision;
+mod parser;
 mod permission_set;
 mod predicate;
 mod role_set;
@@ -0,0 +1,357 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+use std::iter::Peekable;
+use std::str::{CharIndices, FromStr};
+
+use crate::rbac::{Permission, Predicate};
+
+/// Error returned when parsing textual predicate
+///
+/// Positions are byte offsets into the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PredicateParseError {
+    UnexpectedChar {
+        position: usize,
+        found: char,
+    },
+    UnexpectedEnd {
+        position: usize,
+    },
+    /// Parentheses or `!` nested deeper than the parser supports
+    NestingTooDeep {
+        position: usize,
+    },
+    UnknownPermissions(Vec<UnknownPermission>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPermission {
+    pub name: String,
+    pub position: usize,
+}
+
+impl Display for PredicateParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use PredicateParseError::*;
+        match self {
+            UnexpectedChar { position, found } => {
+                write!(f, "unexpected character '{}' at {}", found, position)
+            }
+            UnexpectedEnd { position } => write!(f, "unexpected end of input at {}", position),
+            NestingTooDeep { position } => write!(f, "nesting too deep at {}", position),
+            UnknownPermissions(unknown) => {
+                f.write_str("unknown permissions:")?;
+                for UnknownPermission { name, position } in unknown {
+                    write!(f, " {} at {},", name, position)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for PredicateParseError {}
+
+/// Recursive descent parser, see [`Predicate::parse`] for the syntax
+pub(crate) fn parse<P>(input: &str) -> Result<Predicate<P>, PredicateParseError>
+where
+    P: Permission + FromStr,
+{
+    let mut parser = Parser {
+        input,
+        chars: input.char_indices().peekable(),
+        unknown: Vec::new(),
+        depth: 0,
+    };
+    let predicate = parser.parse_or()?;
+    parser.skip_whitespace();
+    if let Some((position, found)) = parser.chars.next() {
+        return Err(PredicateParseError::UnexpectedChar { position, found });
+    }
+    if !parser.unknown.is_empty() {
+        return Err(PredicateParseError::UnknownPermissions(parser.unknown));
+    }
+    // Unknown permissions are reported above, so every leaf is known here
+    Ok(predicate.expect("known permissions"))
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+    unknown: Vec<UnknownPermission>,
+    /// Number of enclosing parentheses and `!`
+    depth: usize,
+}
+
+/// Limit of nesting, keeps recursion from overflowing the stack
+const MAX_DEPTH: usize = 64;
+
+/// `None` stands for sub-expression which contains unknown permissions
+type Parsed<P> = Result<Option<Predicate<P>>, PredicateParseError>;
+
+impl<'a> Parser<'a> {
+    fn parse_or<P: Permission + FromStr>(&mut self) -> Parsed<P> {
+        let operands = self.parse_operands('|', Self::parse_and)?;
+        Ok(operands.map(|operands| combine(operands, Predicate::Or, Predicate::any)))
+    }
+
+    fn parse_and<P: Permission + FromStr>(&mut self) -> Parsed<P> {
+        let operands = self.parse_operands('&', Self::parse_unary)?;
+        Ok(operands.map(|operands| combine(operands, Predicate::And, Predicate::all)))
+    }
+
+    fn parse_operands<P, F>(
+        &mut self,
+        operator: char,
+        mut parse_operand: F,
+    ) -> Result<Option<Vec<Predicate<P>>>, PredicateParseError>
+    where
+        P: Permission,
+        F: FnMut(&mut Self) -> Parsed<P>,
+    {
+        let mut operands = Some(vec![]);
+        loop {
+            let operand = parse_operand(self)?;
+            operands = operands.zip(operand).map(|(mut vec, operand)| {
+                vec.push(operand);
+                vec
+            });
+            self.skip_whitespace();
+            match self.chars.peek() {
+                Some(&(_, c)) if c == operator => {
+                    self.chars.next();
+                }
+                _ => return Ok(operands),
+            }
+        }
+    }
+
+    fn parse_unary<P: Permission + FromStr>(&mut self) -> Parsed<P> {
+        self.skip_whitespace();
+        match self.chars.next() {
+            Some((position, '!')) => {
+                let predicate = self.nested(position, Self::parse_unary)?;
+                Ok(predicate.map(Predicate::negate))
+            }
+            Some((_, '*')) => Ok(Some(Predicate::Nil)),
+            Some((position, '(')) => {
+                let predicate = self.nested(position, Self::parse_or)?;
+                self.skip_whitespace();
+                match self.chars.next() {
+                    Some((_, ')')) => Ok(predicate),
+                    Some((position, found)) => {
+                        Err(PredicateParseError::UnexpectedChar { position, found })
+                    }
+                    None => Err(self.unexpected_end()),
+                }
+            }
+            Some((start, c)) if is_name_char(c) => {
+                let mut end = start + c.len_utf8();
+                while let Some(&(index, c)) = self.chars.peek() {
+                    if !is_name_char(c) {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    self.chars.next();
+                }
+                let name = &self.input[start..end];
+                match name.parse() {
+                    Ok(permission) => Ok(Some(Predicate::Contains(permission))),
+                    Err(_) => {
+                        self.unknown.push(UnknownPermission {
+                            name: name.to_owned(),
+                            position: start,
+                        });
+                        Ok(None)
+                    }
+                }
+            }
+            Some((position, found)) => Err(PredicateParseError::UnexpectedChar { position, found }),
+            None => Err(self.unexpected_end()),
+        }
+    }
+
+    fn nested<P, F>(&mut self, position: usize, parse: F) -> Parsed<P>
+    where
+        P: Permission,
+        F: FnOnce(&mut Self) -> Parsed<P>,
+    {
+        if self.depth == MAX_DEPTH {
+            return Err(PredicateParseError::NestingTooDeep { position });
+        }
+        self.depth += 1;
+        let predicate = parse(self);
+        self.depth -= 1;
+        predicate
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn unexpected_end(&self) -> PredicateParseError {
+        PredicateParseError::UnexpectedEnd {
+            position: self.input.len(),
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Build predicate from operands, lists of plain permissions become `All` or `Any`
+fn combine<P, F, G>(mut operands: Vec<Predicate<P>>, nested: F, flat: G) -> Predicate<P>
+where
+    P: Permission,
+    F: FnOnce(Box<[Predicate<P>]>) -> Predicate<P>,
+    G: FnOnce(Vec<P>) -> Predicate<P>,
+{
+    if operands.len() == 1 {
+        return operands.remove(0);
+    }
+    let permissions: Option<Vec<P>> = operands
+        .iter()
+        .map(|operand| match operand {
+            Predicate::Contains(permission) => Some(*permission),
+            _ => None,
+        })
+        .collect();
+    match permissions {
+        Some(permissions) => flat(permissions),
+        None => nested(operands.into_boxed_slice()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rbac::test_helpers::TestPermission::{self, *};
+    use crate::rbac::Predicate::*;
+
+    use super::*;
+
+    type TestPredicate = Predicate<TestPermission>;
+
+    #[test]
+    fn parse_precedence() {
+        let predicate: TestPredicate = "Permission1 & (Permission2 | Permission3) & !Permission4"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            predicate,
+            And(Box::new([
+                Contains(Permission1),
+                Any(Box::new([Permission2, Permission3])),
+                Not(Box::new(Contains(Permission4))),
+            ]))
+        );
+        let predicate: TestPredicate = "Permission1 | Permission2 & Permission3".parse().unwrap();
+        assert_eq!(
+            predicate,
+            Or(Box::new([
+                Contains(Permission1),
+                All(Box::new([Permission2, Permission3])),
+            ]))
+        );
+        assert_eq!("*".parse::<TestPredicate>().unwrap(), Nil);
+    }
+
+    #[test]
+    fn display_should_round_trip() {
+        let inputs = [
+            "*",
+            "Permission1",
+            "!Permission1",
+            "Permission1 & Permission2",
+            "Permission1 | Permission2",
+            "Permission1 & (Permission2 | Permission3) & !Permission4",
+            "(Permission1 & Permission2 | Permission3) & Permission4",
+            "!(Permission1 | Permission2)",
+        ];
+        for input in inputs {
+            let predicate: TestPredicate = input.parse().unwrap();
+            assert_eq!(predicate.to_string(), input);
+            assert_eq!(
+                predicate.to_string().parse::<TestPredicate>().unwrap(),
+                predicate
+            );
+        }
+    }
+
+    #[test]
+    fn syntax_error_should_have_position() {
+        let err = "Permission1 & (Permission2"
+            .parse::<TestPredicate>()
+            .unwrap_err();
+        assert_eq!(err, PredicateParseError::UnexpectedEnd { position: 26 });
+        let err = "Permission1 & | Permission2"
+            .parse::<TestPredicate>()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            PredicateParseError::UnexpectedChar {
+                position: 14,
+                found: '|'
+            }
+        );
+        let err = "Permission1 Permission2"
+            .parse::<TestPredicate>()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            PredicateParseError::UnexpectedChar {
+                position: 12,
+                found: 'P'
+            }
+        );
+    }
+
+    #[test]
+    fn deep_nesting_should_be_rejected() {
+        let nested = |open: &str, close: &str, depth| {
+            format!("{}Permission1{}", open.repeat(depth), close.repeat(depth))
+        };
+        assert!(nested("(", ")", MAX_DEPTH).parse::<TestPredicate>().is_ok());
+        assert!(nested("!", "", MAX_DEPTH).parse::<TestPredicate>().is_ok());
+
+        let err = nested("(", ")", 100_000)
+            .parse::<TestPredicate>()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            PredicateParseError::NestingTooDeep {
+                position: MAX_DEPTH
+            }
+        );
+        let err = nested("!", "", 100_000)
+            .parse::<TestPredicate>()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            PredicateParseError::NestingTooDeep {
+                position: MAX_DEPTH
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_permissions_should_be_reported() {
+        let err = "Read & Permission1 | !Write"
+            .parse::<TestPredicate>()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            PredicateParseError::UnknownPermissions(vec![
+                UnknownPermission {
+                    name: String::from("Read"),
+                    position: 0
+                },
+                UnknownPermission {
+                    name: String::from("Write"),
+                    position: 22
+                },
+            ])
+        );
+    }
+}
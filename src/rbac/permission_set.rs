@@ -9,11 +9,15 @@ use tracing::trace;
 use crate::rbac::Permission;
 
 #[derive(Clone)]
-pub struct PermissionSet<T>(BTreeSet<T>);
+pub struct PermissionSet<T> {
+    permissions: BTreeSet<T>,
+    /// Indexes of set bits which do not map to any known permission
+    unknown: Vec<usize>,
+}
 
 impl<T: Debug> Debug for PermissionSet<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        Debug::fmt(&self.0, f)
+        Debug::fmt(&self.permissions, f)
     }
 }
 
@@ -25,14 +29,18 @@ impl<P: Permission> Default for PermissionSet<P> {
 
 impl<P: Permission> PermissionSet<P> {
     pub fn new() -> Self {
-        Self(BTreeSet::new())
+        Self {
+            permissions: BTreeSet::new(),
+            unknown: Vec::new(),
+        }
     }
 
+    /// Encode known permissions as bit vector, unknown bits are not preserved
     pub fn to_bytes(&self) -> Vec<u8> {
         const TO_USIZE_ERROR: &str = "Unable to convert Permission to usize";
         let mut bits = BitVec::<u8, Msb0>::new();
         if let Some(max_permission_id) = self
-            .0
+            .permissions
             .iter()
             .max()
             .map(|p| p.to_usize().expect(TO_USIZE_ERROR))
@@ -46,7 +54,7 @@ impl<P: Permission> PermissionSet<P> {
             );
             bits.resize(len, false);
 
-            for p in self.0.iter() {
+            for p in self.permissions.iter() {
                 let index = p.to_usize().expect(TO_USIZE_ERROR);
                 bits.set(index, true);
             }
@@ -61,41 +69,69 @@ impl<P: Permission> PermissionSet<P> {
     /// In case of identity server is separated from web server.
     /// Identity server may use a newer version of permission library which likely to add newer permissions.
     /// If that permissions are used on outdated web server, this function will return error result
-    /// with known permissions.
+    /// with known permissions. Indexes of unknown permissions are available from [`Self::unknown_bits`].
     pub fn parse_from_bytes(bytes: &[u8]) -> Result<Self, Self> {
+        let set = Self::parse_from_bytes_lossy(bytes);
+        if set.unknown.is_empty() {
+            Ok(set)
+        } else {
+            Err(set)
+        }
+    }
+
+    /// Parse set of permissions from encoded bytes, recording unknown permissions instead of failing
+    pub fn parse_from_bytes_lossy(bytes: &[u8]) -> Self {
         bytes
             .view_bits::<Msb0>()
             .into_iter()
             .enumerate()
-            .filter_map(|(index, bit)| bit.then(|| index))
-            .try_fold(Self::new(), |mut acc, index| {
-                if let Some(permission) = <P as FromPrimitive>::from_usize(index) {
-                    acc.0.insert(permission);
-                    Ok(acc)
-                } else {
-                    Err(acc)
+            .filter_map(|(index, bit)| bit.then_some(index))
+            .fold(Self::new(), |mut acc, index| {
+                match <P as FromPrimitive>::from_usize(index) {
+                    Some(permission) => {
+                        acc.permissions.insert(permission);
+                    }
+                    None => acc.unknown.push(index),
                 }
+                acc
             })
     }
 
+    /// Indexes of bits which were set in encoded bytes but do not map to any known permission
+    pub fn unknown_bits(&self) -> &[usize] {
+        &self.unknown
+    }
+
     pub fn iter(&self) -> Iter<'_, P> {
         Iter {
-            iter: self.0.iter(),
+            iter: self.permissions.iter(),
         }
     }
 
+    /// Whether every permission, including unknown bits, is also held by `other`
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.permissions.is_subset(&other.permissions)
+            && self
+                .unknown
+                .iter()
+                .all(|index| other.unknown.contains(index))
+    }
+
     pub fn from_slice(src: &[P]) -> Self {
         src.iter().copied().collect()
     }
 
     pub(crate) fn inner(&self) -> &BTreeSet<P> {
-        &self.0
+        &self.permissions
     }
 }
 
 impl<T: Ord> FromIterator<T> for PermissionSet<T> {
     fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
-        Self(BTreeSet::from_iter(iter))
+        Self {
+            permissions: BTreeSet::from_iter(iter),
+            unknown: Vec::new(),
+        }
     }
 }
 
@@ -113,7 +149,7 @@ impl<T: Ord, const N: usize> From<[T; N]> for PermissionSet<T> {
 
 impl<A: Ord> Extend<A> for PermissionSet<A> {
     fn extend<T: IntoIterator<Item = A>>(&mut self, iter: T) {
-        self.0.extend(iter)
+        self.permissions.extend(iter)
     }
 }
 
@@ -147,4 +183,39 @@ mod tests {
             .to_bytes();
         assert_eq!(b1, b2);
     }
+
+    #[test]
+    fn subset() {
+        use TestPermission::*;
+        let a = PermissionSet::from([Permission0, Permission1, Permission2]);
+        let b = PermissionSet::from([Permission1, Permission2, Permission3]);
+        let c = PermissionSet::from([Permission1, Permission2]);
+        assert!(c.is_subset(&a) && c.is_subset(&b));
+        assert!(!a.is_subset(&b));
+        assert!(PermissionSet::new().is_subset(&c));
+
+        // Permission0 and unknown permissions at 17 and 23
+        let d =
+            PermissionSet::<TestPermission>::parse_from_bytes_lossy(&[0b1000_0000, 0, 0b0100_0001]);
+        let e = PermissionSet::<TestPermission>::parse_from_bytes_lossy(&[0, 0, 0b0100_0000]);
+        assert!(e.is_subset(&d));
+        assert!(!d.is_subset(&a));
+        assert!(!d.is_subset(&e));
+    }
+
+    #[test]
+    fn parse_unknown_permissions() {
+        // Permission0, Permission8 and unknown permissions at 17 and 23
+        let bytes = [0b1000_0000, 0b1000_0000, 0b0100_0001];
+        let ps = PermissionSet::<TestPermission>::parse_from_bytes(&bytes).unwrap_err();
+        assert_eq!(
+            ps.iter().copied().collect::<Vec<_>>(),
+            vec![TestPermission::Permission0, TestPermission::Permission8]
+        );
+        assert_eq!(ps.unknown_bits(), &[17, 23]);
+        assert_eq!(ps.to_bytes(), vec![0b1000_0000, 0b1000_0000]);
+
+        let ps = PermissionSet::<TestPermission>::parse_from_bytes(&bytes[..2]).unwrap();
+        assert!(ps.unknown_bits().is_empty());
+    }
 }
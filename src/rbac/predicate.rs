@@ -1,16 +1,26 @@
+use std::fmt::{self, Display};
 use std::iter::FromIterator;
+use std::ops::{self, BitAnd, BitOr};
+use std::str::FromStr;
 
 use Predicate::*;
 
+use crate::rbac::parser::{self, PredicateParseError};
 use crate::rbac::Permission;
 use crate::rbac::PermissionSet;
+use crate::rbac::{Decision, Denial};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Predicate<P: Permission> {
     Nil,
     Contains(P),
     Any(Box<[P]>),
     All(Box<[P]>),
+    /// Satisfied if every sub-predicate is satisfied
+    And(Box<[Predicate<P>]>),
+    /// Satisfied if at least one sub-predicate is satisfied
+    Or(Box<[Predicate<P>]>),
+    Not(Box<Predicate<P>>),
 }
 
 impl<P: Permission> Predicate<P> {
@@ -21,6 +31,79 @@ impl<P: Permission> Predicate<P> {
             Contains(permission) => set.contains(permission),
             Any(slice) => slice.iter().any(|permission| set.contains(permission)),
             All(slice) => slice.iter().all(|permission| set.contains(permission)),
+            And(predicates) => predicates.iter().all(|p| p.satisfy(permissions)),
+            Or(predicates) => predicates.iter().any(|p| p.satisfy(permissions)),
+            Not(predicate) => !predicate.satisfy(permissions),
+        }
+    }
+
+    /// Like [`satisfy`](Self::satisfy), explaining why the permissions are not sufficient
+    pub fn evaluate(&self, permissions: &PermissionSet<P>) -> Decision<P> {
+        match self.denial(permissions) {
+            None => Decision::Granted,
+            Some(denial) => Decision::Denied(denial),
+        }
+    }
+
+    fn denial(&self, permissions: &PermissionSet<P>) -> Option<Denial<P>> {
+        let set = permissions.inner();
+        match self {
+            Nil => None,
+            Contains(permission) => {
+                (!set.contains(permission)).then(|| Denial::Missing(Box::new([*permission])))
+            }
+            Any(slice) => (!slice.iter().any(|permission| set.contains(permission)))
+                .then(|| Denial::AnyOf(slice.clone())),
+            All(slice) => {
+                let missing: Box<[P]> = slice
+                    .iter()
+                    .filter(|permission| !set.contains(permission))
+                    .copied()
+                    .collect();
+                (!missing.is_empty()).then_some(Denial::Missing(missing))
+            }
+            And(predicates) => {
+                let mut denials: Vec<_> = predicates
+                    .iter()
+                    .filter_map(|p| p.denial(permissions))
+                    .collect();
+                match denials.len() {
+                    0 | 1 => denials.pop(),
+                    _ => Some(Denial::AllOf(denials.into_boxed_slice())),
+                }
+            }
+            // Granted as soon as one alternative is granted
+            Or(predicates) => predicates
+                .iter()
+                .map(|p| p.denial(permissions))
+                .collect::<Option<Box<[_]>>>()
+                .map(Denial::OneOf),
+            Not(predicate) => predicate
+                .satisfy(permissions)
+                .then(|| Denial::Forbidden((**predicate).clone())),
+        }
+    }
+
+    /// Combine with `other`, nested `And` on the left hand side is flattened
+    pub fn and<T: Into<Self>>(self, other: T) -> Self {
+        match self {
+            And(predicates) => And(push(predicates, other.into())),
+            predicate => And(Box::new([predicate, other.into()])),
+        }
+    }
+
+    /// Combine with `other`, nested `Or` on the left hand side is flattened
+    pub fn or<T: Into<Self>>(self, other: T) -> Self {
+        match self {
+            Or(predicates) => Or(push(predicates, other.into())),
+            predicate => Or(Box::new([predicate, other.into()])),
+        }
+    }
+
+    pub fn negate(self) -> Self {
+        match self {
+            Not(predicate) => *predicate,
+            predicate => Not(Box::new(predicate)),
         }
     }
 
@@ -37,6 +120,158 @@ impl<P: Permission> Predicate<P> {
     }
 }
 
+impl<P: Permission + FromStr> Predicate<P> {
+    /// Parse predicate from expression such as `Read & (Write | Admin) & !Suspended`
+    ///
+    /// `!` binds tighter than `&`, which binds tighter than `|`.
+    /// `*` is the predicate without any requirement.
+    pub fn parse(input: &str) -> Result<Self, PredicateParseError> {
+        parser::parse(input)
+    }
+}
+
+impl<P: Permission + FromStr> FromStr for Predicate<P> {
+    type Err = PredicateParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+const OR_PRECEDENCE: u8 = 1;
+const AND_PRECEDENCE: u8 = 2;
+const NOT_PRECEDENCE: u8 = 3;
+
+/// Formatter of a single permission
+pub(crate) type FmtPermission<P> = fn(&P, &mut fmt::Formatter<'_>) -> fmt::Result;
+
+impl<P: Permission> Predicate<P> {
+    fn fmt_with_precedence(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        precedence: u8,
+        fmt_permission: FmtPermission<P>,
+    ) -> fmt::Result {
+        let fmt_item = |p: &P, f: &mut fmt::Formatter<'_>, _| fmt_permission(p, f);
+        let fmt_predicate = |p: &Self, f: &mut fmt::Formatter<'_>, precedence| {
+            p.fmt_with_precedence(f, precedence, fmt_permission)
+        };
+        match self {
+            Nil => f.write_str("*"),
+            Contains(permission) => fmt_permission(permission, f),
+            All(slice) => fmt_list(f, slice, fmt_item, AND_PRECEDENCE, precedence),
+            Any(slice) => fmt_list(f, slice, fmt_item, OR_PRECEDENCE, precedence),
+            And(predicates) => fmt_list(f, predicates, fmt_predicate, AND_PRECEDENCE, precedence),
+            Or(predicates) => fmt_list(f, predicates, fmt_predicate, OR_PRECEDENCE, precedence),
+            Not(predicate) => {
+                f.write_str("!")?;
+                predicate.fmt_with_precedence(f, NOT_PRECEDENCE, fmt_permission)
+            }
+        }
+    }
+
+    /// Text form with permissions written by discriminant, e.g. `1 & !2`
+    pub(crate) fn to_discriminant_string(&self) -> String {
+        struct Discriminants<'a, P: Permission>(&'a Predicate<P>);
+
+        impl<P: Permission> Display for Discriminants<'_, P> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt_with_precedence(f, 0, fmt_discriminant)
+            }
+        }
+
+        Discriminants(self).to_string()
+    }
+}
+
+/// Format permission by its discriminant, for permissions which do not implement `Display`
+pub(crate) fn fmt_discriminant<P: Permission>(
+    permission: &P,
+    f: &mut fmt::Formatter<'_>,
+) -> fmt::Result {
+    match permission.to_u64() {
+        Some(discriminant) => Display::fmt(&discriminant, f),
+        None => f.write_str("?"),
+    }
+}
+
+/// Format operands joined by `&` or `|`, add parentheses if outer operator binds tighter
+fn fmt_list<T, F>(
+    f: &mut fmt::Formatter<'_>,
+    items: &[T],
+    fmt_item: F,
+    list_precedence: u8,
+    precedence: u8,
+) -> fmt::Result
+where
+    F: Fn(&T, &mut fmt::Formatter<'_>, u8) -> fmt::Result,
+{
+    let (separator, empty) = if list_precedence == AND_PRECEDENCE {
+        (" & ", "*")
+    } else {
+        (" | ", "!*")
+    };
+    match items {
+        [] => f.write_str(empty),
+        [item] => fmt_item(item, f, precedence),
+        items => {
+            let parenthesize = list_precedence < precedence;
+            if parenthesize {
+                f.write_str("(")?;
+            }
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(separator)?;
+                }
+                fmt_item(item, f, list_precedence)?;
+            }
+            if parenthesize {
+                f.write_str(")")?;
+            }
+            Ok(())
+        }
+    }
+}
+
+impl<P: Permission + Display> Display for Predicate<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_with_precedence(f, 0, Display::fmt)
+    }
+}
+
+fn push<P: Permission>(
+    predicates: Box<[Predicate<P>]>,
+    predicate: Predicate<P>,
+) -> Box<[Predicate<P>]> {
+    let mut vec = predicates.into_vec();
+    vec.push(predicate);
+    vec.into_boxed_slice()
+}
+
+impl<P: Permission, T: Into<Self>> BitAnd<T> for Predicate<P> {
+    type Output = Self;
+
+    fn bitand(self, rhs: T) -> Self::Output {
+        self.and(rhs)
+    }
+}
+
+impl<P: Permission, T: Into<Self>> BitOr<T> for Predicate<P> {
+    type Output = Self;
+
+    fn bitor(self, rhs: T) -> Self::Output {
+        self.or(rhs)
+    }
+}
+
+impl<P: Permission> ops::Not for Predicate<P> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        self.negate()
+    }
+}
+
 impl<P: Permission> From<P> for Predicate<P> {
     fn from(p: P) -> Self {
         Contains(p)
@@ -121,4 +356,61 @@ mod tests {
         assert!(!TestPredicate::all([Permission1, Permission2])
             .satisfy(&[Permission2, Permission3].into()));
     }
+
+    #[test]
+    fn and_should_satisfy_every_sub_predicate() {
+        let predicate = TestPredicate::contains(Permission1)
+            .and(TestPredicate::any([Permission2, Permission3]));
+        assert!(predicate.satisfy(&[Permission1, Permission2].into()));
+        assert!(predicate.satisfy(&[Permission1, Permission3].into()));
+        assert!(!predicate.satisfy(&[Permission1].into()));
+        assert!(!predicate.satisfy(&[Permission2, Permission3].into()));
+    }
+
+    #[test]
+    fn or_should_satisfy_any_sub_predicate() {
+        // (Permission1 and Permission2) or Permission3
+        let predicate = TestPredicate::all([Permission1, Permission2]) | Permission3;
+        assert!(predicate.satisfy(&[Permission1, Permission2].into()));
+        assert!(predicate.satisfy(&[Permission3].into()));
+        assert!(!predicate.satisfy(&[Permission1].into()));
+        assert!(!predicate.satisfy(&[].into()));
+    }
+
+    #[test]
+    fn not_should_negate() {
+        // Permission1 but not Permission4
+        let predicate =
+            TestPredicate::contains(Permission1) & !TestPredicate::contains(Permission4);
+        assert!(predicate.satisfy(&[Permission1].into()));
+        assert!(!predicate.satisfy(&[Permission1, Permission4].into()));
+        assert!(!predicate.satisfy(&[Permission4].into()));
+        assert!(!TestPredicate::Nil.negate().satisfy(&[].into()));
+    }
+
+    #[test]
+    fn combinators_should_flatten() {
+        let predicate = TestPredicate::contains(Permission1) & Permission2 & Permission3;
+        assert_eq!(
+            predicate,
+            And(Box::new([
+                Contains(Permission1),
+                Contains(Permission2),
+                Contains(Permission3)
+            ]))
+        );
+        let predicate = TestPredicate::contains(Permission1) | Permission2 | Permission3;
+        assert_eq!(
+            predicate,
+            Or(Box::new([
+                Contains(Permission1),
+                Contains(Permission2),
+                Contains(Permission3)
+            ]))
+        );
+        assert_eq!(
+            !!TestPredicate::contains(Permission1),
+            Contains(Permission1)
+        );
+    }
 }
@@ -1,4 +1,6 @@
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
 use std::iter::FromIterator;
 
 use crate::rbac::traits::Role;
@@ -7,22 +9,118 @@ use crate::rbac::PermissionSet;
 #[derive(Default)]
 pub struct RoleSet<R: Role>(BTreeSet<R>);
 
+impl<R: Role + Debug> Debug for RoleSet<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
 impl<R: Role> RoleSet<R> {
     pub fn new() -> Self {
         Self(Default::default())
     }
 
+    /// Collect permissions of every role including inherited ones
+    ///
+    /// Roles are visited only once, so cyclic hierarchy does not loop forever.
+    /// Use [`expand`](Self::expand) to detect cycles.
     pub fn to_permission_set(&self) -> PermissionSet<R::Permission> {
-        self.0
-            .iter()
-            .map(Role::permissions)
-            .fold(PermissionSet::new(), |mut acc, permissions| {
+        self.closure().into_iter().map(Role::permissions).fold(
+            PermissionSet::new(),
+            |mut acc, permissions| {
                 acc.extend(permissions.iter().copied());
                 acc
-            })
+            },
+        )
+    }
+
+    /// Map each permission to the roles which grant it directly
+    ///
+    /// Inherited roles are included, so the result tells which role in the hierarchy
+    /// contributed the permission.
+    pub fn permission_sources(&self) -> BTreeMap<R::Permission, Vec<&R>> {
+        let mut sources = BTreeMap::<_, Vec<_>>::new();
+        for role in self.closure() {
+            for &permission in role.permissions() {
+                sources.entry(permission).or_default().push(role);
+            }
+        }
+        sources
+    }
+
+    /// Return set of roles with every inherited role, or the first cycle found
+    pub fn expand(&self) -> Result<Self, RoleCycleError<R>>
+    where
+        R: Clone,
+    {
+        let mut visited = BTreeMap::new();
+        let mut path = Vec::new();
+        for role in &self.0 {
+            visit(role, &mut visited, &mut path).map_err(|cycle| RoleCycleError {
+                cycle: cycle.into_iter().cloned().collect(),
+            })?;
+        }
+        Ok(visited.into_keys().cloned().collect())
+    }
+
+    fn closure(&self) -> BTreeSet<&R> {
+        let mut closure = BTreeSet::new();
+        let mut stack: Vec<&R> = self.0.iter().collect();
+        while let Some(role) = stack.pop() {
+            if closure.insert(role) {
+                stack.extend(role.parents());
+            }
+        }
+        closure
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum VisitState {
+    InProgress,
+    Done,
+}
+
+/// Depth first search, return roles that form a cycle if found
+fn visit<'a, R: Role>(
+    role: &'a R,
+    visited: &mut BTreeMap<&'a R, VisitState>,
+    path: &mut Vec<&'a R>,
+) -> Result<(), Vec<&'a R>> {
+    match visited.get(role) {
+        Some(VisitState::Done) => return Ok(()),
+        Some(VisitState::InProgress) => {
+            let start = path.iter().position(|r| *r == role).unwrap_or_default();
+            let mut cycle = path[start..].to_vec();
+            cycle.push(role);
+            return Err(cycle);
+        }
+        None => {}
+    }
+    visited.insert(role, VisitState::InProgress);
+    path.push(role);
+    for parent in role.parents() {
+        visit(parent, visited, path)?;
     }
+    path.pop();
+    visited.insert(role, VisitState::Done);
+    Ok(())
 }
 
+/// Role hierarchy contains a cycle, first and last roles of `cycle` are the same
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoleCycleError<R> {
+    pub cycle: Vec<R>,
+}
+
+impl<R: Debug> Display for RoleCycleError<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cyclic role hierarchy: {:?}", self.cycle)
+    }
+}
+
+impl<R: Debug> Error for RoleCycleError<R> {}
+
 impl<R: Role> FromIterator<R> for RoleSet<R> {
     fn from_iter<I: IntoIterator<Item = R>>(iter: I) -> Self {
         Self(BTreeSet::from_iter(iter))
@@ -37,7 +135,8 @@ impl<R: Role> From<Vec<R>> for RoleSet<R> {
 
 #[cfg(test)]
 mod tests {
-    use crate::rbac::test_helpers::TestRole;
+    use crate::rbac::test_helpers::TestPermission::*;
+    use crate::rbac::test_helpers::TestRole::{self, *};
 
     use super::*;
 
@@ -47,4 +146,33 @@ mod tests {
         rs.0.insert(TestRole::Role0);
         assert_eq!(rs.to_permission_set().inner().len(), 2);
     }
+
+    #[test]
+    fn inherited_permissions() {
+        let rs: RoleSet<TestRole> = vec![Role1].into();
+        let ps = rs.to_permission_set();
+        assert_eq!(
+            ps.iter().copied().collect::<Vec<_>>(),
+            vec![Permission0, Permission1, Permission5]
+        );
+        assert_eq!(rs.expand().unwrap().0, [Role0, Role1].into());
+    }
+
+    #[test]
+    fn permission_sources() {
+        let rs: RoleSet<TestRole> = vec![Role1, Role2].into();
+        let sources = rs.permission_sources();
+        assert_eq!(sources[&Permission0], vec![&Role0]);
+        assert_eq!(sources[&Permission3], vec![&Role2]);
+        assert_eq!(sources[&Permission5], vec![&Role1]);
+    }
+
+    #[test]
+    fn cycle_detection() {
+        let rs: RoleSet<TestRole> = vec![Role0, Role3].into();
+        let err = rs.expand().unwrap_err();
+        assert_eq!(err.cycle, vec![Role3, Role4, Role3]);
+        // Cycle must not prevent collecting permissions
+        assert_eq!(rs.to_permission_set().inner().len(), 4);
+    }
 }
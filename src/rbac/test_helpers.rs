@@ -16,6 +16,7 @@ use crate::rbac::traits::Role;
     num_derive::ToPrimitive,
     strum::Display,
     strum::EnumCount,
+    strum::EnumString,
 )]
 #[repr(u16)]
 pub enum TestPermission {
@@ -46,6 +47,8 @@ pub enum TestRole {
     Role0,
     Role1,
     Role2,
+    Role3,
+    Role4,
 }
 
 impl Role for TestRole {
@@ -54,6 +57,17 @@ impl Role for TestRole {
     fn permissions(&self) -> &[Self::Permission] {
         PERMISSIONS.get(self).map(Vec::as_slice).unwrap_or_default()
     }
+
+    fn parents(&self) -> &[Self] {
+        use TestRole::*;
+        match self {
+            Role1 => &[Role0],
+            // Role3 and Role4 form a cycle
+            Role3 => &[Role4],
+            Role4 => &[Role3],
+            _ => &[],
+        }
+    }
 }
 
 type RolePermissionsMap = BTreeMap<TestRole, Vec<TestPermission>>;
@@ -65,6 +79,9 @@ fn create_role_permissions() -> RolePermissionsMap {
     use TestRole::*;
     let mut map = RolePermissionsMap::new();
     map.insert(Role0, vec![Permission0, Permission1]);
+    map.insert(Role1, vec![Permission5]);
     map.insert(Role2, vec![Permission3, Permission4]);
+    map.insert(Role3, vec![Permission6]);
+    map.insert(Role4, vec![Permission7]);
     map
 }
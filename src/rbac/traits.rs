@@ -1,12 +1,31 @@
 use num_traits::{FromPrimitive, ToPrimitive};
 
+use super::Predicate;
+
 /// A marker trait for enums where variants do not have payloads
 ///
 /// ToPrimitive must produce unique value (same value that use in Ord)
+///
+/// `#[derive(Permission)]`, available with `derive` feature, implements this trait and
+/// every required trait from enum discriminants.
 pub trait Permission: Copy + Clone + Ord + FromPrimitive + ToPrimitive {}
 
 pub trait Role: Ord + FromPrimitive {
     type Permission: Permission;
 
     fn permissions(&self) -> &[Self::Permission];
+
+    /// Roles whose permissions are inherited by this role
+    fn parents(&self) -> &[Self] {
+        &[]
+    }
+}
+
+/// Predicate known at compile time
+///
+/// Framework extractors take it as a type parameter, e.g. `Authorized<Token, CanEditOrders>`.
+pub trait Requirement {
+    type Permission: Permission;
+
+    fn predicate() -> Predicate<Self::Permission>;
 }
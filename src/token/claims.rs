@@ -0,0 +1,343 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Map, Value};
+
+use crate::rbac::{Permission, PermissionSet};
+
+use super::AccessToken;
+
+/// Permission bitset as encoded by `PermissionSet::to_bytes`, in base64url
+const PERMISSIONS_CLAIM: &str = "permissions";
+
+/// Encoding of `exp`, `nbf` and `iat` claims
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TimeFormat {
+    /// Seconds since the Unix epoch, as used by JWT
+    NumericDate,
+    /// RFC 3339 date and time, as used by PASETO
+    DateTime,
+}
+
+/// Claims stamped into an access token by [`TokenIssuer`](super::TokenIssuer) right before signing
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub struct IssueClaims {
+    pub issued_at: SystemTime,
+    pub expires_at: Option<SystemTime>,
+}
+
+impl IssueClaims {
+    pub(crate) fn new(issued_at: SystemTime, lifetime: Option<Duration>) -> Self {
+        Self {
+            issued_at,
+            expires_at: lifetime.map(|lifetime| issued_at + lifetime),
+        }
+    }
+}
+
+/// JSON claims of JWT and PASETO tokens, passed to [`AccessToken::from_claims`]
+///
+/// Tokens are issued with the registered claims returned by [`AccessToken`] getters and
+/// `permissions` claim holding the permission bitset.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct TokenClaims<P: Permission> {
+    pub issuer: Option<String>,
+    pub subject: Option<String>,
+    pub audience: Vec<String>,
+    pub expires_at: Option<SystemTime>,
+    pub not_before: Option<SystemTime>,
+    pub issued_at: Option<SystemTime>,
+    pub token_id: Option<String>,
+    /// Permissions decoded with `PermissionSet::parse_from_bytes_lossy`
+    pub permissions: PermissionSet<P>,
+    /// Every claim of the token, including the ones above
+    pub json: Map<String, Value>,
+}
+
+impl<P: Permission> TokenClaims<P> {
+    /// Parse claims from JSON object
+    ///
+    /// Return `None` if a registered claim has an unexpected type or `permissions` claim is
+    /// missing.
+    pub(crate) fn parse(json: &[u8], time_format: TimeFormat) -> Option<Self> {
+        let json: Map<String, Value> = serde_json::from_slice(json).ok()?;
+        let string = |name| match json.get(name) {
+            Some(value) => value.as_str().map(|s| Some(s.to_owned())),
+            None => Some(None),
+        };
+        let time = |name| match json.get(name) {
+            Some(value) => time_format.parse(value).map(Some),
+            None => Some(None),
+        };
+        let audience = match json.get("aud") {
+            Some(Value::String(audience)) => vec![audience.clone()],
+            Some(Value::Array(audience)) => audience
+                .iter()
+                .map(|value| value.as_str().map(String::from))
+                .collect::<Option<_>>()?,
+            Some(_) => return None,
+            None => Vec::new(),
+        };
+        let permissions = json.get(PERMISSIONS_CLAIM)?.as_str()?;
+        let permissions = base64::decode_config(permissions, base64::URL_SAFE_NO_PAD).ok()?;
+        Some(Self {
+            issuer: string("iss")?,
+            subject: string("sub")?,
+            audience,
+            expires_at: time("exp")?,
+            not_before: time("nbf")?,
+            issued_at: time("iat")?,
+            token_id: string("jti")?,
+            permissions: PermissionSet::parse_from_bytes_lossy(&permissions),
+            json,
+        })
+    }
+}
+
+/// Registered claims and permissions of the token
+pub(crate) fn encode_claims<A: AccessToken>(
+    token: &A,
+    time_format: TimeFormat,
+) -> Map<String, Value> {
+    let mut claims = Map::new();
+    let mut insert = |name: &str, value: Option<Value>| {
+        if let Some(value) = value {
+            claims.insert(name.to_owned(), value);
+        }
+    };
+    insert("iss", token.issuer().map(Value::from));
+    insert("sub", token.subject().map(Value::from));
+    insert(
+        "aud",
+        (!token.audience().is_empty()).then(|| Value::from(token.audience())),
+    );
+    insert(
+        "exp",
+        token.expires_at().and_then(|t| time_format.encode(t)),
+    );
+    insert(
+        "nbf",
+        token.not_before().and_then(|t| time_format.encode(t)),
+    );
+    insert("iat", token.issued_at().and_then(|t| time_format.encode(t)));
+    insert("jti", token.token_id().map(Value::from));
+    let permissions = token.permissions().to_bytes();
+    insert(
+        PERMISSIONS_CLAIM,
+        Some(base64::encode_config(permissions, base64::URL_SAFE_NO_PAD).into()),
+    );
+    claims
+}
+
+impl TimeFormat {
+    /// Encode time in whole seconds, `None` if it is before the Unix epoch
+    fn encode(self, time: SystemTime) -> Option<Value> {
+        let secs = time.duration_since(UNIX_EPOCH).ok()?.as_secs();
+        match self {
+            TimeFormat::NumericDate => Some(secs.into()),
+            TimeFormat::DateTime => format_date_time(secs).map(Value::from),
+        }
+    }
+
+    fn parse(self, value: &Value) -> Option<SystemTime> {
+        match self {
+            // Numeric date may have a fractional part
+            TimeFormat::NumericDate => UNIX_EPOCH.checked_add(match value.as_u64() {
+                Some(secs) => Duration::from_secs(secs),
+                None => Duration::try_from_secs_f64(value.as_f64()?).ok()?,
+            }),
+            TimeFormat::DateTime => parse_date_time(value.as_str()?),
+        }
+    }
+}
+
+/// Format seconds since the Unix epoch as `YYYY-MM-DDTHH:MM:SSZ`
+fn format_date_time(secs: u64) -> Option<String> {
+    let days = i64::try_from(secs / 86400).ok()?;
+    let (year, month, day) = civil_from_days(days);
+    if year > 9999 {
+        return None;
+    }
+    let secs = secs % 86400;
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    ))
+}
+
+/// Parse RFC 3339 date and time, e.g. `2022-01-01T00:00:00.5+07:00`
+fn parse_date_time(input: &str) -> Option<SystemTime> {
+    let number = |start: usize, len: usize| -> Option<i64> {
+        let digits = input.get(start..start + len)?;
+        digits
+            .bytes()
+            .all(|b| b.is_ascii_digit())
+            .then(|| digits.parse().ok())?
+    };
+    let bytes = input.as_bytes();
+    let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+    if bytes.len() < 20
+        || !separators.iter().all(|&(i, c)| bytes[i] == c)
+        || !matches!(bytes[10], b'T' | b't')
+    {
+        return None;
+    }
+    let (year, month, day) = (number(0, 4)?, number(5, 2)?, number(8, 2)?);
+    let (hour, minute, second) = (number(11, 2)?, number(14, 2)?, number(17, 2)?);
+    let days = days_from_civil(year, month, day);
+    if civil_from_days(days) != (year, month, day) || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    // 1. fraction of a second, digits beyond nanoseconds are ignored
+    let mut rest = &input[19..];
+    let mut nanos = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = fraction.bytes().take_while(u8::is_ascii_digit).count();
+        if len == 0 {
+            return None;
+        }
+        for (i, digit) in fraction[..len].bytes().take(9).enumerate() {
+            nanos += u32::from(digit - b'0') * 10u32.pow(8 - i as u32);
+        }
+        rest = &fraction[len..];
+    }
+    // 2. offset from UTC
+    let start = input.len() - rest.len();
+    let offset = match rest.as_bytes() {
+        [b'Z' | b'z'] => 0,
+        [sign @ (b'+' | b'-'), _, _, b':', _, _] => {
+            let offset = number(start + 1, 2)? * 3600 + number(start + 4, 2)? * 60;
+            if *sign == b'+' {
+                offset
+            } else {
+                -offset
+            }
+        }
+        _ => return None,
+    };
+    let secs = days * 86400 + hour * 3600 + minute * 60 + second - offset;
+    UNIX_EPOCH.checked_add(Duration::new(u64::try_from(secs).ok()?, nanos))
+}
+
+/// Civil date of days since the Unix epoch, in proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months are counted from March, so that leap day is the last day of the year
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Days since the Unix epoch of civil date, inverse of [`civil_from_days`]
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = (month + 9) % 12;
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::rbac::test_helpers::TestPermission::{self, Permission1, Permission2};
+
+    use super::*;
+
+    fn parse(json: Value) -> Option<TokenClaims<TestPermission>> {
+        TokenClaims::parse(json.to_string().as_bytes(), TimeFormat::NumericDate)
+    }
+
+    #[test]
+    fn parse_registered_claims() {
+        let permissions = PermissionSet::from([Permission1, Permission2]).to_bytes();
+        let permissions = base64::encode_config(permissions, base64::URL_SAFE_NO_PAD);
+        let claims = parse(json!({
+            "iss": "https://id.example.com",
+            "aud": "api",
+            "exp": 1700000000.5,
+            "iat": 1600000000,
+            "permissions": permissions,
+            "tenant": "acme",
+        }))
+        .unwrap();
+        assert_eq!(claims.issuer.as_deref(), Some("https://id.example.com"));
+        assert_eq!(claims.audience, ["api"]);
+        assert_eq!(
+            claims.expires_at,
+            Some(UNIX_EPOCH + Duration::from_millis(1_700_000_000_500))
+        );
+        assert_eq!(
+            claims.issued_at,
+            Some(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+        );
+        assert_eq!(claims.subject, None);
+        assert!(claims.permissions.inner().contains(&Permission2));
+        assert_eq!(claims.json["tenant"], "acme");
+
+        assert!(parse(json!({ "permissions": permissions, "sub": 1 })).is_none());
+        assert!(parse(json!({ "permissions": permissions, "aud": ["api", 1] })).is_none());
+        assert!(parse(json!({ "permissions": permissions, "exp": -1 })).is_none());
+        assert!(parse(json!({ "permissions": permissions, "exp": 1e300 })).is_none());
+        assert!(parse(json!({ "sub": "user-1" })).is_none());
+    }
+
+    #[test]
+    fn date_time_claims() {
+        let parse = |s: &str| TimeFormat::DateTime.parse(&Value::from(s));
+        let time = UNIX_EPOCH + Duration::from_secs(1_640_995_200);
+        assert_eq!(
+            TimeFormat::DateTime.encode(time),
+            Some(json!("2022-01-01T00:00:00Z"))
+        );
+        assert_eq!(parse("2022-01-01T00:00:00Z"), Some(time));
+        assert_eq!(parse("2022-01-01T00:00:00+00:00"), Some(time));
+        assert_eq!(parse("2022-01-01t07:30:00+07:30"), Some(time));
+        assert_eq!(parse("2021-12-31T23:00:00-01:00"), Some(time));
+        assert_eq!(
+            parse("2022-01-01T00:00:00.25Z"),
+            Some(time + Duration::from_millis(250))
+        );
+
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(
+            TimeFormat::DateTime.encode(leap_day),
+            Some(json!("2000-02-29T00:00:00Z"))
+        );
+        assert_eq!(parse("2000-02-29T00:00:00Z"), Some(leap_day));
+        assert_eq!(
+            TimeFormat::DateTime.encode(UNIX_EPOCH),
+            Some(json!("1970-01-01T00:00:00Z"))
+        );
+
+        for invalid in [
+            "2022-01-01",
+            "2022-01-01T00:00:00",
+            "2022-01-01 00:00:00Z",
+            "2022-02-30T00:00:00Z",
+            "2022-01-01T24:00:00Z",
+            "2022-01-01T00:00:00.Z",
+            "2022-01-01T00:00:00+0000",
+            "1969-12-31T23:59:59Z",
+            "+022-01-01T00:00:00Z",
+        ] {
+            assert_eq!(parse(invalid), None, "{}", invalid);
+        }
+    }
+}
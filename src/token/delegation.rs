@@ -0,0 +1,349 @@
+//! Delegated tokens which can be attenuated without calling the identity server
+//!
+//! A chain of blocks in the style of Biscuit. The first block holds the token issued by the
+//! identity server, every following block holds a token derived from the previous one which
+//! must not exceed it. Each block carries a fresh Ed25519 public key which signs the next block,
+//! and the encoded token carries the private key of the last block as proof of possession.
+//! Holders can append blocks but cannot remove them, since the proof of a shorter chain is
+//! never revealed.
+
+use crate::crypto::{Algorithm, PrivateKey, PublicKey, SignedMessage, Signer};
+
+use super::AccessToken;
+
+const VERSION: &str = "d1";
+const SEPARATOR: char = '.';
+/// Separates signatures of delegated tokens from signatures of any other message
+const CONTEXT: &[u8] = b"tokidator delegated token";
+
+struct Block {
+    payload: Vec<u8>,
+    next_key: PublicKey,
+    signature: Vec<u8>,
+}
+
+pub(crate) struct DelegatedToken {
+    key_id: Option<String>,
+    algorithm: Algorithm,
+    blocks: Vec<Block>,
+    proof: PrivateKey,
+}
+
+impl DelegatedToken {
+    /// Sign the first block, return `None` if the random number generator fails
+    pub(crate) fn issue(
+        payload: Vec<u8>,
+        key_id: Option<String>,
+        signer: &(impl Signer + ?Sized),
+    ) -> Option<Self> {
+        let proof = PrivateKey::generate()?;
+        let next_key = proof.public_key();
+        let signature = signer.sign(&signing_input(&[], &payload, &next_key));
+        Some(Self {
+            key_id,
+            algorithm: signer.algorithm(),
+            blocks: vec![Block {
+                payload,
+                next_key,
+                signature,
+            }],
+            proof,
+        })
+    }
+
+    /// Append block signed by the proof, return `None` if the random number generator fails
+    pub(crate) fn attenuate(&mut self, payload: Vec<u8>) -> Option<()> {
+        let proof = PrivateKey::generate()?;
+        let next_key = proof.public_key();
+        let previous_signature = &self.last_block().signature;
+        let signature = self
+            .proof
+            .sign(&signing_input(previous_signature, &payload, &next_key));
+        self.blocks.push(Block {
+            payload,
+            next_key,
+            signature,
+        });
+        self.proof = proof;
+        Some(())
+    }
+
+    pub(crate) fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    /// Payload of every block, starting from the token issued by the identity server
+    pub(crate) fn payloads(&self) -> impl Iterator<Item = &[u8]> {
+        self.blocks.iter().map(|block| block.payload.as_slice())
+    }
+
+    pub(crate) fn last_payload(&self) -> &[u8] {
+        &self.last_block().payload
+    }
+
+    /// First block as signed by the identity server
+    pub(crate) fn root_signed_message(&self) -> SignedMessage {
+        let root = &self.blocks[0];
+        SignedMessage::from_parts(
+            signing_input(&[], &root.payload, &root.next_key),
+            root.signature.clone(),
+            self.key_id.clone(),
+            self.algorithm,
+        )
+    }
+
+    /// Index of the first block after the first one which is not signed by the previous
+    /// block, or of the last block if the proof does not match its key
+    pub(crate) fn unverified_block(&self) -> Option<usize> {
+        let forged = self.blocks.windows(2).position(|pair| {
+            let [previous, block] = pair else {
+                unreachable!()
+            };
+            let message = signing_input(&previous.signature, &block.payload, &block.next_key);
+            !previous.next_key.verify(&message, &block.signature)
+        });
+        let proof_matches =
+            self.proof.public_key().as_bytes() == self.last_block().next_key.as_bytes();
+        forged
+            .map(|position| position + 1)
+            .or_else(|| (!proof_matches).then(|| self.blocks.len() - 1))
+    }
+
+    /// Encode to `d1.base64(key_id).base64(algorithm name)`, followed by
+    /// `.base64(payload).base64(next_key).base64(signature)` of every block and `.proof`
+    pub(crate) fn encode(&self) -> String {
+        let mut output = String::from(VERSION);
+        let mut push = |segment: &[u8]| {
+            output.push(SEPARATOR);
+            base64::encode_config_buf(segment, base64::URL_SAFE_NO_PAD, &mut output);
+        };
+        push(self.key_id.as_deref().unwrap_or_default().as_bytes());
+        push(self.algorithm.name().as_bytes());
+        for block in &self.blocks {
+            push(&block.payload);
+            push(block.next_key.as_bytes());
+            push(&block.signature);
+        }
+        output.push(SEPARATOR);
+        output.push_str(&self.proof.to_base64());
+        output
+    }
+
+    pub(crate) fn decode(input: &[u8]) -> Option<Self> {
+        let decode = |input| base64::decode_config(input, base64::URL_SAFE_NO_PAD).ok();
+        let mut segments: Vec<_> = std::str::from_utf8(input).ok()?.split(SEPARATOR).collect();
+        let proof = PrivateKey::from_base64(segments.pop()?)?;
+        let [version, key_id, algorithm, blocks @ ..] = segments.as_slice() else {
+            return None;
+        };
+        if *version != VERSION || blocks.is_empty() || blocks.len() % 3 != 0 {
+            return None;
+        }
+        let key_id = match *key_id {
+            "" => None,
+            key_id => Some(String::from_utf8(decode(key_id)?).ok()?),
+        };
+        let algorithm = Algorithm::from_name(std::str::from_utf8(&decode(algorithm)?).ok()?)?;
+        let blocks = blocks
+            .chunks(3)
+            .map(|block| {
+                Some(Block {
+                    payload: decode(block[0])?,
+                    next_key: PublicKey::from_bytes(&decode(block[1])?),
+                    signature: decode(block[2])?,
+                })
+            })
+            .collect::<Option<_>>()?;
+        Some(Self {
+            key_id,
+            algorithm,
+            blocks,
+            proof,
+        })
+    }
+
+    fn last_block(&self) -> &Block {
+        self.blocks
+            .last()
+            .expect("delegated token has at least one block")
+    }
+}
+
+/// Derive a token from the last token of a delegated token, see
+/// [`TokenIssuer::issue_delegated`](super::TokenIssuer::issue_delegated)
+///
+/// `derived` must hold a subset of the permissions of the last token and must not outlive it,
+/// see [`TokenValidator::validate_delegated`](super::TokenValidator::validate_delegated) for
+/// the exact rules. A wider `derived` is rejected rather than clamped to the last token, so the
+/// caller decides which permissions to give up. Return `None` if the token is malformed,
+/// `derived` is not narrower or the random number generator fails. Signatures are not
+/// verified.
+pub fn attenuate<A: AccessToken, T: AsRef<[u8]>>(token: T, derived: &A) -> Option<String> {
+    let mut delegated_token = DelegatedToken::decode(token.as_ref())?;
+    let parent = A::from_bytes(delegated_token.last_payload()).ok()?;
+    if !is_attenuation(derived, &parent) {
+        return None;
+    }
+    delegated_token.attenuate(derived.to_bytes())?;
+    Some(delegated_token.encode())
+}
+
+/// Check that `derived` does not exceed `parent`
+///
+/// Permissions must be a subset, validity period must lie within the parent's one, issuer and
+/// subject must be kept and audience may only be narrowed.
+pub(crate) fn is_attenuation<A: AccessToken>(derived: &A, parent: &A) -> bool {
+    let narrower_permissions = derived.permissions().is_subset(parent.permissions());
+    let expires_in_time = parent
+        .expires_at()
+        .is_none_or(|parent| derived.expires_at().is_some_and(|time| time <= parent));
+    let starts_in_time = parent
+        .not_before()
+        .is_none_or(|parent| derived.not_before().is_some_and(|time| parent <= time));
+    let narrower_audience = parent.audience().is_empty()
+        || (!derived.audience().is_empty()
+            && derived
+                .audience()
+                .iter()
+                .all(|audience| parent.audience().contains(audience)));
+    narrower_permissions
+        && expires_in_time
+        && starts_in_time
+        && narrower_audience
+        && derived.issuer() == parent.issuer()
+        && derived.subject() == parent.subject()
+}
+
+/// Length prefixed encoding of the pieces, so that no two blocks have the same signing input
+fn signing_input(previous_signature: &[u8], payload: &[u8], next_key: &PublicKey) -> Vec<u8> {
+    let mut output = Vec::new();
+    for piece in [CONTEXT, previous_signature, payload, next_key.as_bytes()] {
+        output.extend_from_slice(&(piece.len() as u64).to_le_bytes());
+        output.extend_from_slice(piece);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime};
+
+    use crate::rbac::test_helpers::TestPermission::{self, Permission1, Permission2, Permission3};
+    use crate::rbac::{PermissionSet, Predicate};
+    use crate::token::test_utils::{make_validator, private_key, TestAccessToken, ValidateResult};
+    use crate::token::{InMemoryRevocationStore, TokenIssuer};
+    use crate::Error::*;
+
+    use super::*;
+
+    fn expires_in(secs: u64) -> SystemTime {
+        SystemTime::now() + Duration::from_secs(secs)
+    }
+
+    fn issue_parent() -> String {
+        let token = TestAccessToken::new(vec![Permission1, Permission2].into(), false)
+            .with_subject("user-1")
+            .with_token_id("token-1")
+            .with_expires_at(expires_in(60));
+        TokenIssuer::new(private_key())
+            .issue_delegated(token)
+            .unwrap()
+    }
+
+    fn derive(
+        permissions: PermissionSet<TestPermission>,
+        expires_at: SystemTime,
+    ) -> TestAccessToken {
+        TestAccessToken::new(permissions, false)
+            .with_subject("user-1")
+            .with_expires_at(expires_at)
+    }
+
+    #[test]
+    fn attenuated_token_should_pass_validation() {
+        let parent = issue_parent();
+        let validator = make_validator();
+        let x: ValidateResult = validator.validate_delegated(&parent);
+        let granted =
+            |x: &TestAccessToken, p| validator.authorize(x, Predicate::contains(p)).is_granted();
+        assert!(granted(&x.unwrap(), Permission2));
+
+        let permissions = PermissionSet::from([Permission1]);
+        let child = attenuate(&parent, &derive(permissions.clone(), expires_in(30))).unwrap();
+        let x: ValidateResult = validator.validate_delegated(&child);
+        let x = x.unwrap();
+        assert!(granted(&x, Permission1));
+        assert!(!granted(&x, Permission2));
+
+        let grandchild = attenuate(&child, &derive(permissions, expires_in(10))).unwrap();
+        let x: ValidateResult = make_validator().validate_delegated(&grandchild);
+        assert!(x.is_ok());
+        // delegated tokens are not plain signed messages
+        let x: ValidateResult = make_validator().validate(&grandchild);
+        assert_auth_error!(x, InvalidSignedMessage);
+    }
+
+    #[test]
+    fn derived_token_should_not_exceed_parent() {
+        let parent = issue_parent();
+        let wider = derive(vec![Permission1, Permission3].into(), expires_in(30));
+        assert!(attenuate(&parent, &wider).is_none());
+        let longer = derive(vec![Permission1].into(), expires_in(120));
+        assert!(attenuate(&parent, &longer).is_none());
+        let other_subject = derive(vec![Permission1].into(), expires_in(30)).with_subject("user-2");
+        assert!(attenuate(&parent, &other_subject).is_none());
+
+        // holder bypassing `attenuate` is caught by validator
+        let mut delegated_token = DelegatedToken::decode(parent.as_bytes()).unwrap();
+        delegated_token.attenuate(wider.to_bytes()).unwrap();
+        let x: ValidateResult = make_validator().validate_delegated(delegated_token.encode());
+        assert_auth_error!(x, InvalidAttenuation);
+        let metadata = x.unwrap_err().metadata().clone();
+        assert_eq!(metadata.delegation_block, Some(1));
+        assert_eq!(metadata.key_id, None);
+    }
+
+    #[test]
+    fn blocks_cannot_be_removed_or_modified() {
+        let parent = issue_parent();
+        let child = attenuate(&parent, &derive(vec![Permission1].into(), expires_in(30))).unwrap();
+        let segments: Vec<_> = child.split(SEPARATOR).collect();
+
+        // drop the last block but keep the proof
+        let stripped = [&segments[..6], &segments[9..]].concat().join(".");
+        let x: ValidateResult = make_validator().validate_delegated(stripped);
+        assert_auth_error!(x, SignatureVerificationFail);
+        assert_eq!(x.unwrap_err().metadata().delegation_block, Some(0));
+
+        // replace payload of the last block
+        let wider = derive(vec![Permission1, Permission2].into(), expires_in(30));
+        let mut forged = segments.clone();
+        let payload = base64::encode_config(wider.to_bytes(), base64::URL_SAFE_NO_PAD);
+        forged[6] = &payload;
+        let x: ValidateResult = make_validator().validate_delegated(forged.join("."));
+        assert_auth_error!(x, SignatureVerificationFail);
+        assert_eq!(x.unwrap_err().metadata().delegation_block, Some(1));
+
+        let x: ValidateResult = make_validator().validate_delegated(format!("{child}.AAAA"));
+        assert_auth_error!(x, InvalidSignedMessage);
+    }
+
+    #[test]
+    fn revoking_parent_should_revoke_derived_token() {
+        let store = Arc::new(InMemoryRevocationStore::new());
+        let mut validator = make_validator();
+        validator.set_revocation_store(Some(store.clone()));
+        let child = attenuate(
+            issue_parent(),
+            &derive(vec![Permission1].into(), expires_in(30)),
+        )
+        .unwrap();
+        let x: ValidateResult = validator.validate_delegated(&child);
+        assert!(x.is_ok());
+
+        store.update(|list| list.revoke_token_id("token-1"));
+        let x: ValidateResult = validator.validate_delegated(&child);
+        assert_auth_error!(x, RevokedAccessToken);
+    }
+}
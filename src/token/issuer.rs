@@ -0,0 +1,252 @@
+use std::time::{Duration, SystemTime};
+
+use serde_json::Value;
+
+use crate::crypto::{EncryptedMessage, EncryptionKey, SignedMessage, Signer};
+
+use super::claims::{encode_claims, TimeFormat};
+use super::delegation::DelegatedToken;
+use super::{jwt, paseto, AccessToken, IssueClaims};
+
+pub struct TokenIssuer {
+    private_key: Box<dyn Signer>,
+    key_id: Option<String>,
+    encryption_key: Option<(String, EncryptionKey)>,
+    config: IssuanceConfig,
+}
+
+#[derive(Clone, Copy)]
+pub struct IssuanceConfig {
+    /// Lifetime of issued token, `None` leaves expiration to the token itself
+    pub lifetime: Option<Duration>,
+    /// Source of current time, tests can replace it to issue at a fixed time
+    pub clock: fn() -> SystemTime,
+    /// Issue tokens in the unversioned format for validators which are not upgraded yet
+    pub legacy_format: bool,
+}
+
+impl Default for IssuanceConfig {
+    fn default() -> Self {
+        Self {
+            lifetime: None,
+            clock: SystemTime::now,
+            legacy_format: false,
+        }
+    }
+}
+
+impl TokenIssuer {
+    pub fn new(private_key: impl Signer + 'static) -> Self {
+        Self::with_config(private_key, IssuanceConfig::default())
+    }
+
+    pub fn with_config(private_key: impl Signer + 'static, config: IssuanceConfig) -> Self {
+        Self {
+            private_key: Box::new(private_key),
+            key_id: None,
+            encryption_key: None,
+            config,
+        }
+    }
+
+    /// Create issuer which records `key_id` in every issued token
+    pub fn with_key_id(key_id: impl Into<String>, private_key: impl Signer + 'static) -> Self {
+        Self {
+            key_id: Some(key_id.into()),
+            ..Self::new(private_key)
+        }
+    }
+
+    /// Replace current signing key, tokens issued from now on are signed by the new key
+    ///
+    /// The new key may use a different algorithm, validators have to allow it beforehand.
+    pub fn rotate(&mut self, key_id: impl Into<String>, private_key: impl Signer + 'static) {
+        self.private_key = Box::new(private_key);
+        self.key_id = Some(key_id.into());
+    }
+
+    /// Set key for [`issue_encrypted`](Self::issue_encrypted), replacing the current one
+    pub fn set_encryption_key(&mut self, key_id: impl Into<String>, key: EncryptionKey) {
+        self.encryption_key = Some((key_id.into(), key));
+    }
+
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    pub fn issue<A: AccessToken>(&self, token: A) -> String {
+        self.issue_config(token, self.config)
+    }
+
+    pub fn issue_config<A: AccessToken>(&self, mut token: A, config: IssuanceConfig) -> String {
+        // 1. stamp issuance claims
+        token.stamp_claims(&IssueClaims::new((config.clock)(), config.lifetime));
+        // 2. sign with current key and encode
+        let message = token.to_bytes();
+        let signed_message = match &self.key_id {
+            Some(key_id) => {
+                SignedMessage::create_with_key_id(message, key_id.clone(), &*self.private_key)
+            }
+            None => SignedMessage::create(message, &*self.private_key),
+        };
+        if config.legacy_format {
+            signed_message.encode_legacy()
+        } else {
+            signed_message.encode()
+        }
+    }
+
+    /// Issue signed token encrypted so that clients cannot read permissions,
+    /// see [`TokenValidator::validate_encrypted`]
+    ///
+    /// Return `None` if there is no encryption key or the random number generator fails.
+    ///
+    /// [`TokenValidator::validate_encrypted`]: super::TokenValidator::validate_encrypted
+    pub fn issue_encrypted<A: AccessToken>(&self, token: A) -> Option<String> {
+        self.issue_encrypted_config(token, self.config)
+    }
+
+    pub fn issue_encrypted_config<A: AccessToken>(
+        &self,
+        token: A,
+        config: IssuanceConfig,
+    ) -> Option<String> {
+        let (key_id, encryption_key) = self.encryption_key.as_ref()?;
+        let signed_token = self.issue_config(token, config);
+        EncryptedMessage::seal(signed_token.as_bytes(), key_id.clone(), encryption_key)
+            .map(|encrypted_message| encrypted_message.encode())
+    }
+
+    /// Issue token which holders can attenuate with [`attenuate`](super::attenuate) and
+    /// pass downstream, see [`TokenValidator::validate_delegated`]
+    ///
+    /// Return `None` if the random number generator fails.
+    ///
+    /// [`TokenValidator::validate_delegated`]: super::TokenValidator::validate_delegated
+    pub fn issue_delegated<A: AccessToken>(&self, token: A) -> Option<String> {
+        self.issue_delegated_config(token, self.config)
+    }
+
+    pub fn issue_delegated_config<A: AccessToken>(
+        &self,
+        mut token: A,
+        config: IssuanceConfig,
+    ) -> Option<String> {
+        // 1. stamp issuance claims
+        token.stamp_claims(&IssueClaims::new((config.clock)(), config.lifetime));
+        // 2. sign the first block with current key
+        DelegatedToken::issue(token.to_bytes(), self.key_id.clone(), &*self.private_key)
+            .map(|delegated_token| delegated_token.encode())
+    }
+
+    /// Issue JWT in JWS compact serialization, see [`TokenValidator::validate_jwt`]
+    ///
+    /// [`TokenValidator::validate_jwt`]: super::TokenValidator::validate_jwt
+    pub fn issue_jwt<A: AccessToken>(&self, token: A) -> String {
+        self.issue_jwt_config(token, self.config)
+    }
+
+    pub fn issue_jwt_config<A: AccessToken>(&self, mut token: A, config: IssuanceConfig) -> String {
+        // 1. stamp issuance claims
+        token.stamp_claims(&IssueClaims::new((config.clock)(), config.lifetime));
+        // 2. sign header and claims with current key
+        jwt::encode(&token, self.key_id.as_deref(), &*self.private_key)
+    }
+
+    /// Issue PASETO v4.public token, see [`TokenValidator::validate_paseto`]
+    ///
+    /// If the issuer has key id, `kid` member is added to `footer`, which must then be empty
+    /// or a JSON object. Return `None` if the signing key is not an Ed25519 key or `footer`
+    /// cannot carry the key id.
+    ///
+    /// [`TokenValidator::validate_paseto`]: super::TokenValidator::validate_paseto
+    pub fn issue_paseto<A: AccessToken>(
+        &self,
+        token: A,
+        footer: &[u8],
+        implicit_assertion: &[u8],
+    ) -> Option<String> {
+        self.issue_paseto_config(token, footer, implicit_assertion, self.config)
+    }
+
+    pub fn issue_paseto_config<A: AccessToken>(
+        &self,
+        mut token: A,
+        footer: &[u8],
+        implicit_assertion: &[u8],
+        config: IssuanceConfig,
+    ) -> Option<String> {
+        // 1. stamp issuance claims
+        token.stamp_claims(&IssueClaims::new((config.clock)(), config.lifetime));
+        // 2. sign payload, footer and implicit assertion with current key
+        let key_id_footer;
+        let footer = match &self.key_id {
+            Some(key_id) => {
+                key_id_footer = paseto::key_id_footer(footer, key_id)?;
+                &key_id_footer
+            }
+            None => footer,
+        };
+        let claims = Value::Object(encode_claims(&token, TimeFormat::DateTime));
+        paseto::encode(
+            claims.to_string().as_bytes(),
+            footer,
+            implicit_assertion,
+            &*self.private_key,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rbac::test_helpers::TestPermission::{Permission1, Permission2};
+    use crate::token::test_utils::{make_validator, private_key, TestAccessToken, ValidateResult};
+
+    use super::*;
+
+    fn make_issuer(config: IssuanceConfig) -> TokenIssuer {
+        TokenIssuer::with_config(private_key(), config)
+    }
+
+    #[test]
+    fn issued_token_should_pass_validation() {
+        let issuer = make_issuer(Default::default());
+        let token = issuer.issue(TestAccessToken::new(
+            vec![Permission1, Permission2].into(),
+            false,
+        ));
+        let x: ValidateResult = make_validator().validate(token);
+        let x = x.expect("valid access token");
+        assert!(x.permissions().inner().contains(&Permission1));
+        assert!(x.permissions().inner().contains(&Permission2));
+    }
+
+    #[test]
+    fn issue_should_stamp_claims() {
+        let lifetime = Duration::from_secs(60);
+        let issuer = make_issuer(IssuanceConfig {
+            lifetime: Some(lifetime),
+            ..Default::default()
+        });
+        let before = SystemTime::now();
+        let token = issuer.issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let x: TestAccessToken = make_validator().validate(token).unwrap();
+        let issued_at = x.issued_at().expect("issued_at claim");
+        assert!(issued_at + Duration::from_secs(1) > before);
+        assert_eq!(x.expires_at(), Some(issued_at + lifetime));
+    }
+
+    #[test]
+    fn issue_config_should_override_default_lifetime() {
+        let issuer = make_issuer(IssuanceConfig {
+            lifetime: Some(Duration::from_secs(60)),
+            ..Default::default()
+        });
+        let token = issuer.issue_config(
+            TestAccessToken::new(vec![Permission1].into(), false),
+            IssuanceConfig::default(),
+        );
+        let x: TestAccessToken = make_validator().validate(token).unwrap();
+        assert_eq!(x.expires_at(), None);
+    }
+}
@@ -0,0 +1,187 @@
+//! JWS compact serialization of access tokens
+//!
+//! Claims carry the registered claims of the token and its permission bitset, so that
+//! gateways and third-party tools can read and mint JWT. [`TokenValidator`] builds the
+//! [`AccessToken`] from them with [`AccessToken::from_claims`].
+//!
+//! [`TokenValidator`]: super::TokenValidator
+
+use serde_json::{json, Map, Value};
+
+use crate::crypto::{Algorithm, SignedMessage, Signer};
+
+use super::claims::{encode_claims, TimeFormat};
+use super::AccessToken;
+
+pub(crate) fn encode<A: AccessToken>(
+    token: &A,
+    key_id: Option<&str>,
+    signer: &(impl Signer + ?Sized),
+) -> String {
+    let mut header = json!({
+        "alg": signer.algorithm().name(),
+        "typ": "JWT",
+    });
+    if let Some(key_id) = key_id {
+        header["kid"] = key_id.into();
+    }
+    let mut output = base64_encode(header.to_string().as_bytes());
+    output.push('.');
+    let claims = Value::Object(encode_claims(token, TimeFormat::NumericDate));
+    output.push_str(&base64_encode(claims.to_string().as_bytes()));
+    let signature = signer.sign(output.as_bytes());
+    output.push('.');
+    output.push_str(&base64_encode(&signature));
+    output
+}
+
+/// Decode header and signature, the message of returned `SignedMessage` is the JWS signing input
+pub(crate) fn decode(token: &[u8]) -> Option<SignedMessage> {
+    let token = std::str::from_utf8(token).ok()?;
+    let (signing_input, signature) = token.rsplit_once('.')?;
+    let (header, payload) = signing_input.split_once('.')?;
+    if payload.contains('.') {
+        return None;
+    }
+    let header: Map<String, Value> = serde_json::from_slice(&base64_decode(header)?).ok()?;
+    // Extensions which must be understood are not supported
+    if header.contains_key("crit") {
+        return None;
+    }
+    let algorithm = Algorithm::from_name(header.get("alg")?.as_str()?)?;
+    let key_id = match header.get("kid") {
+        Some(key_id) => Some(key_id.as_str()?.to_owned()),
+        None => None,
+    };
+    Some(SignedMessage::from_parts(
+        signing_input.as_bytes().to_vec(),
+        base64_decode(signature)?,
+        key_id,
+        algorithm,
+    ))
+}
+
+/// Decode JSON claims from JWS signing input
+pub(crate) fn payload(signing_input: &[u8]) -> Option<Vec<u8>> {
+    let (_, payload) = std::str::from_utf8(signing_input).ok()?.split_once('.')?;
+    base64_decode(payload)
+}
+
+fn base64_encode(input: &[u8]) -> String {
+    base64::encode_config(input, base64::URL_SAFE_NO_PAD)
+}
+
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    base64::decode_config(input, base64::URL_SAFE_NO_PAD).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use crate::rbac::test_helpers::TestPermission::{self, Permission1, Permission2};
+    use crate::rbac::PermissionSet;
+    use crate::token::test_utils::{make_validator, private_key, TestAccessToken, ValidateResult};
+    use crate::token::TokenIssuer;
+    use crate::Error::*;
+
+    use super::*;
+
+    fn test_token() -> TestAccessToken {
+        TestAccessToken::new(vec![Permission1, Permission2].into(), false)
+            .with_issuer("https://id.example.com")
+            .with_audience(&["api"])
+            .with_subject("user-1")
+            .with_expires_at(SystemTime::now() + Duration::from_secs(60))
+    }
+
+    fn decode_segment(segment: &str) -> Value {
+        serde_json::from_slice(&base64_decode(segment).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn header_and_claims() {
+        let token = TokenIssuer::with_key_id("k1", private_key()).issue_jwt(test_token());
+        let segments: Vec<_> = token.split('.').collect();
+        assert_eq!(segments.len(), 3);
+        assert_eq!(
+            decode_segment(segments[0]),
+            json!({"alg": "EdDSA", "typ": "JWT", "kid": "k1"})
+        );
+        let claims = decode_segment(segments[1]);
+        assert_eq!(claims["iss"], "https://id.example.com");
+        assert_eq!(claims["aud"], json!(["api"]));
+        assert_eq!(claims["sub"], "user-1");
+        assert!(claims["exp"].is_u64());
+        assert!(claims["iat"].is_u64());
+        let permissions = base64_decode(claims["permissions"].as_str().unwrap()).unwrap();
+        let permissions = PermissionSet::<TestPermission>::parse_from_bytes(&permissions).unwrap();
+        assert_eq!(
+            permissions.to_bytes(),
+            test_token().permissions().to_bytes()
+        );
+        assert_eq!(claims.as_object().unwrap().len(), 6);
+    }
+
+    #[test]
+    fn validate_issued_jwt() {
+        let token = TokenIssuer::new(private_key()).issue_jwt(test_token());
+        let x: ValidateResult = make_validator().validate_jwt(&token);
+        let x = x.unwrap();
+        assert!(x.permissions().inner().contains(&Permission2));
+        assert_eq!(x.subject(), Some("user-1"));
+
+        // native validation does not accept JWT and vice versa
+        let x: ValidateResult = make_validator().validate(&token);
+        assert!(x.is_err());
+        let native = TokenIssuer::new(private_key()).issue(test_token());
+        let x: ValidateResult = make_validator().validate_jwt(native);
+        assert_auth_error!(x, InvalidSignedMessage);
+    }
+
+    #[test]
+    fn reject_malformed_jwt() {
+        let token = TokenIssuer::new(private_key()).issue_jwt(test_token());
+        let (_, rest) = token.split_once('.').unwrap();
+        let validate = |token: String| -> ValidateResult { make_validator().validate_jwt(token) };
+
+        let x = validate(format!("{token}."));
+        assert_auth_error!(x, InvalidSignedMessage);
+        let x = validate(rest.to_owned());
+        assert_auth_error!(x, InvalidSignedMessage);
+        let crit = base64_encode(br#"{"alg":"EdDSA","crit":["exp"]}"#);
+        let x = validate(format!("{crit}.{rest}"));
+        assert_auth_error!(x, InvalidSignedMessage);
+        let none = base64_encode(br#"{"alg":"none"}"#);
+        let x = validate(format!("{none}.{rest}"));
+        assert_auth_error!(x, InvalidSignedMessage);
+        // header is covered by the signature
+        let header = base64_encode(br#"{"typ":"JWT","alg":"EdDSA"}"#);
+        let x = validate(format!("{header}.{rest}"));
+        assert_auth_error!(x, SignatureVerificationFail);
+    }
+
+    #[test]
+    fn validate_jwt_minted_by_other_tools() {
+        let sign = |claims: Value| -> ValidateResult {
+            let header = base64_encode(br#"{"alg":"EdDSA"}"#);
+            let payload = base64_encode(claims.to_string().as_bytes());
+            let signing_input = format!("{header}.{payload}");
+            let signature = base64_encode(&private_key().sign(signing_input.as_bytes()));
+            make_validator().validate_jwt(format!("{signing_input}.{signature}"))
+        };
+        let permissions = PermissionSet::from([Permission2]).to_bytes();
+
+        let x = sign(json!({ "sub": "user-1", "permissions": base64_encode(&permissions) }));
+        let x = x.unwrap();
+        assert!(x.permissions().inner().contains(&Permission2));
+        assert_eq!(x.subject(), Some("user-1"));
+
+        let x = sign(json!({ "sub": "user-1" }));
+        assert_auth_error!(x, InvalidAccessToken);
+        let x = sign(json!({ "exp": "tomorrow", "permissions": base64_encode(&permissions) }));
+        assert_auth_error!(x, InvalidAccessToken);
+        let x = sign(json!({ "exp": 1, "permissions": base64_encode(&permissions) }));
+        assert_auth_error!(x, ExpiredAccessToken);
+    }
+}
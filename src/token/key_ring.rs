@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::crypto::{PublicKey, Verifier, VerifyingKey};
+
+/// Set of trusted public keys, looked up by key id
+///
+/// Tokens signed without key id are verified with the default key, if any.
+/// Keys of different algorithms can be mixed.
+#[derive(Clone, Default)]
+pub struct KeyRing {
+    keys: BTreeMap<String, KeyEntry>,
+    default_key: Option<Arc<dyn Verifier>>,
+}
+
+#[derive(Clone)]
+struct KeyEntry {
+    key: Arc<dyn Verifier>,
+    retired_at: Option<SystemTime>,
+}
+
+impl KeyRing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key_id: impl Into<String>, key: impl Verifier + 'static) {
+        let entry = KeyEntry {
+            key: Arc::new(key),
+            retired_at: None,
+        };
+        self.keys.insert(key_id.into(), entry);
+    }
+
+    pub fn remove(&mut self, key_id: &str) -> Option<Arc<dyn Verifier>> {
+        self.keys.remove(key_id).map(|entry| entry.key)
+    }
+
+    /// Set key which is used to verify tokens without key id
+    pub fn set_default_key(&mut self, key: Option<Arc<dyn Verifier>>) {
+        self.default_key = key;
+    }
+
+    /// Stop trusting the key once `grace_period` has elapsed
+    ///
+    /// Tokens signed by a retired key keep validating during the grace period so that
+    /// outstanding tokens have time to expire. Return `false` if there is no such key.
+    pub fn retire(&mut self, key_id: &str, grace_period: Duration) -> bool {
+        self.retire_at(key_id, SystemTime::now() + grace_period)
+    }
+
+    pub fn retire_at(&mut self, key_id: &str, time: SystemTime) -> bool {
+        match self.keys.get_mut(key_id) {
+            Some(entry) => {
+                entry.retired_at = Some(time);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Find a key which is trusted at the given time
+    pub fn find(&self, key_id: Option<&str>, now: SystemTime) -> Option<&dyn Verifier> {
+        match key_id {
+            Some(key_id) => self
+                .keys
+                .get(key_id)
+                .filter(|entry| entry.retired_at.is_none_or(|time| now < time))
+                .map(|entry| &*entry.key),
+            None => self.default_key.as_deref(),
+        }
+    }
+}
+
+impl From<PublicKey> for KeyRing {
+    fn from(key: PublicKey) -> Self {
+        Self {
+            keys: BTreeMap::new(),
+            default_key: Some(Arc::new(key)),
+        }
+    }
+}
+
+impl From<VerifyingKey> for KeyRing {
+    fn from(key: VerifyingKey) -> Self {
+        Self {
+            keys: BTreeMap::new(),
+            default_key: Some(Arc::new(key)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::tests::get_test_public_key;
+
+    use super::*;
+
+    fn test_key() -> PublicKey {
+        PublicKey::from_base64(&get_test_public_key()).unwrap()
+    }
+
+    #[test]
+    fn find_by_key_id() {
+        let now = SystemTime::now();
+        let mut ring = KeyRing::new();
+        ring.insert("k1", test_key());
+        assert!(ring.find(Some("k1"), now).is_some());
+        assert!(ring.find(Some("k2"), now).is_none());
+        assert!(ring.find(None, now).is_none());
+    }
+
+    #[test]
+    fn default_key_is_used_without_key_id() {
+        let now = SystemTime::now();
+        let ring = KeyRing::from(test_key());
+        assert!(ring.find(None, now).is_some());
+        assert!(ring.find(Some("k1"), now).is_none());
+    }
+
+    #[test]
+    fn retired_key_is_trusted_during_grace_period() {
+        let now = SystemTime::now();
+        let mut ring = KeyRing::new();
+        ring.insert("k1", test_key());
+        assert!(ring.retire_at("k1", now + Duration::from_secs(60)));
+        assert!(ring.find(Some("k1"), now).is_some());
+        assert!(ring
+            .find(Some("k1"), now + Duration::from_secs(60))
+            .is_none());
+        assert!(!ring.retire("k2", Duration::ZERO));
+    }
+}
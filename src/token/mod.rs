@@ -1,8 +1,29 @@
+pub use claims::{IssueClaims, TokenClaims};
+pub use delegation::attenuate;
+pub use issuer::{IssuanceConfig, TokenIssuer};
+pub use key_ring::KeyRing;
+pub use paseto::paseto_footer;
+pub use revocation::{
+    FileRevocationStore, InMemoryRevocationStore, ParseRevocationListError, RevocationList,
+    RevocationQuery, RevocationStore,
+};
 pub use traits::AccessToken;
-pub use validator::{TokenValidator, ValidationConfig};
+pub use validation_error::{TokenMetadata, ValidationError, ValidationStage};
+pub use validator::{
+    HmacTokenValidator, TokenValidator, UnknownPermissionPolicy, ValidationConfig, VerificationKeys,
+};
 
 #[cfg(test)]
+#[macro_use]
 pub(crate) mod test_utils;
 
+mod claims;
+mod delegation;
+mod issuer;
+mod jwt;
+mod key_ring;
+mod paseto;
+mod revocation;
 mod traits;
+mod validation_error;
 mod validator;
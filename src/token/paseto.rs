@@ -0,0 +1,246 @@
+//! PASETO v4.public tokens
+//!
+//! The payload is a JSON object of registered claims, with times in RFC 3339 format, and
+//! the permission bitset, see [`TokenClaims`](super::TokenClaims). It is signed with Ed25519
+//! over the pre-authentication encoding of header, payload, footer and implicit assertion.
+//! A footer which is a JSON object with `kid` member selects the key.
+
+use serde_json::{Map, Value};
+
+use crate::crypto::{Algorithm, SignedMessage, Signer};
+
+const HEADER: &str = "v4.public.";
+const SIGNATURE_LEN: usize = 64;
+
+/// Sign the payload, return `None` if the key is not an Ed25519 key
+pub(crate) fn encode(
+    payload: &[u8],
+    footer: &[u8],
+    implicit_assertion: &[u8],
+    signer: &(impl Signer + ?Sized),
+) -> Option<String> {
+    if signer.algorithm() != Algorithm::Ed25519 {
+        return None;
+    }
+    let signature = signer.sign(&pre_auth_encode(&[
+        HEADER.as_bytes(),
+        payload,
+        footer,
+        implicit_assertion,
+    ]));
+    let mut output = String::from(HEADER);
+    output.push_str(&base64_encode(&[payload, &signature].concat()));
+    if !footer.is_empty() {
+        output.push('.');
+        output.push_str(&base64_encode(footer));
+    }
+    Some(output)
+}
+
+/// Decode token into the signed pre-authentication encoding and the payload
+pub(crate) fn decode(token: &[u8], implicit_assertion: &[u8]) -> Option<(SignedMessage, Vec<u8>)> {
+    let (body, footer) = split(token)?;
+    let split_at = body.len().checked_sub(SIGNATURE_LEN)?;
+    let (payload, signature) = body.split_at(split_at);
+    let message = pre_auth_encode(&[HEADER.as_bytes(), payload, &footer, implicit_assertion]);
+    let signed_message = SignedMessage::from_parts(
+        message,
+        signature.to_vec(),
+        footer_key_id(&footer),
+        Algorithm::Ed25519,
+    );
+    Some((signed_message, payload.to_vec()))
+}
+
+/// Extract footer of PASETO v4.public token
+///
+/// The footer is not authenticated until the token is validated.
+pub fn paseto_footer<T: AsRef<[u8]>>(token: T) -> Option<Vec<u8>> {
+    split(token.as_ref()).map(|(_, footer)| footer)
+}
+
+/// Footer for tokens signed by a key with identifier, `kid` member is added to `footer`
+///
+/// Return `None` if `footer` is neither empty nor a JSON object, or it names another key.
+pub(crate) fn key_id_footer(footer: &[u8], key_id: &str) -> Option<Vec<u8>> {
+    let mut footer: Map<String, Value> = if footer.is_empty() {
+        Map::new()
+    } else {
+        serde_json::from_slice(footer).ok()?
+    };
+    match footer.get("kid") {
+        Some(kid) if kid != key_id => return None,
+        _ => footer.insert("kid".to_owned(), key_id.into()),
+    };
+    Some(Value::Object(footer).to_string().into_bytes())
+}
+
+fn footer_key_id(footer: &[u8]) -> Option<String> {
+    let footer: Map<String, Value> = serde_json::from_slice(footer).ok()?;
+    footer.get("kid")?.as_str().map(str::to_owned)
+}
+
+/// Split token into decoded body and footer
+fn split(token: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let token = std::str::from_utf8(token).ok()?.strip_prefix(HEADER)?;
+    let (body, footer) = match token.split_once('.') {
+        Some((body, footer)) => (body, base64_decode(footer).filter(|f| !f.is_empty())?),
+        None => (token, Vec::new()),
+    };
+    Some((base64_decode(body)?, footer))
+}
+
+/// Pre-authentication encoding, `PAE` in PASETO specification
+fn pre_auth_encode(pieces: &[&[u8]]) -> Vec<u8> {
+    fn le64(n: usize) -> [u8; 8] {
+        // Most significant bit is cleared for interoperability with signed integers
+        ((n as u64) & (u64::MAX >> 1)).to_le_bytes()
+    }
+    let mut output = le64(pieces.len()).to_vec();
+    for piece in pieces {
+        output.extend_from_slice(&le64(piece.len()));
+        output.extend_from_slice(piece);
+    }
+    output
+}
+
+fn base64_encode(input: &[u8]) -> String {
+    base64::encode_config(input, base64::URL_SAFE_NO_PAD)
+}
+
+fn base64_decode(input: &str) -> Option<Vec<u8>> {
+    base64::decode_config(input, base64::URL_SAFE_NO_PAD).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crypto::tests::get_test_public_key;
+    use crate::crypto::{PrivateKey, PublicKey};
+    use crate::rbac::test_helpers::TestPermission::{Permission1, Permission2};
+    use crate::token::test_utils::{make_validator, private_key, TestAccessToken, ValidateResult};
+    use crate::token::{AccessToken, KeyRing, TokenIssuer, TokenValidator, ValidationConfig};
+    use crate::Error::*;
+
+    use super::*;
+
+    fn test_token() -> TestAccessToken {
+        TestAccessToken::new(vec![Permission1, Permission2].into(), false).with_subject("user-1")
+    }
+
+    #[test]
+    fn pre_auth_encoding() {
+        assert_eq!(pre_auth_encode(&[]), b"\x00\x00\x00\x00\x00\x00\x00\x00");
+        assert_eq!(
+            pre_auth_encode(&[b""]),
+            b"\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00"
+        );
+        assert_eq!(
+            pre_auth_encode(&[b"test"]),
+            b"\x01\x00\x00\x00\x00\x00\x00\x00\x04\x00\x00\x00\x00\x00\x00\x00test"
+        );
+    }
+
+    #[test]
+    fn verify_specification_test_vector() {
+        // 4-S-1 from the PASETO test vectors
+        let seed = "b4cbfb43df4ce210727d953e4a713307fa19bb7d9f85041438d9e11b942a3774";
+        let seed: Vec<u8> = (0..seed.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&seed[i..i + 2], 16).unwrap())
+            .collect();
+        let key = PrivateKey::from_bytes(&seed).unwrap();
+        let payload = br#"{"data":"this is a signed message","exp":"2022-01-01T00:00:00+00:00"}"#;
+        let token = "v4.public.eyJkYXRhIjoidGhpcyBpcyBhIHNpZ25lZCBtZXNzYWdlIiwiZXhwIjoiMjAyMi0wMS0wMVQwMDowMDowMCswMDowMCJ9bg_XBBzds8lTZShVlwwKSgeKpLT3yukTw6JUz3W4h_ExsQV-P0V54zemZDcAxFaSeef1QlXEFtkqxT1ciiQEDA";
+        assert_eq!(encode(payload, b"", b"", &key).unwrap(), token);
+        let (signed_message, decoded) = decode(token.as_bytes(), b"").unwrap();
+        assert_eq!(decoded, payload);
+        assert!(signed_message.verify(&key.public_key()));
+    }
+
+    #[test]
+    fn validate_issued_paseto() {
+        let issuer = TokenIssuer::new(private_key());
+        let token = issuer.issue_paseto(test_token(), b"footer", b"").unwrap();
+        assert!(token.starts_with("v4.public."));
+        assert_eq!(paseto_footer(&token).unwrap(), b"footer");
+        let (_, payload) = decode(token.as_bytes(), b"").unwrap();
+        let claims: Value = serde_json::from_slice(&payload).unwrap();
+        assert_eq!(claims["sub"], "user-1");
+        assert!(claims["iat"].as_str().unwrap().ends_with('Z'));
+        assert!(claims["permissions"].is_string());
+
+        let x: ValidateResult = make_validator().validate_paseto(&token);
+        let x = x.unwrap();
+        assert!(x.permissions().inner().contains(&Permission2));
+        assert_eq!(x.subject(), Some("user-1"));
+
+        // footer is authenticated
+        let (body, _) = token.rsplit_once('.').unwrap();
+        let tampered = format!("{body}.{}", base64_encode(b"other"));
+        let x: ValidateResult = make_validator().validate_paseto(tampered);
+        assert_auth_error!(x, SignatureVerificationFail);
+        // native format is not PASETO
+        let x: ValidateResult = make_validator().validate_paseto(issuer.issue(test_token()));
+        assert_auth_error!(x, InvalidSignedMessage);
+    }
+
+    #[test]
+    fn implicit_assertion() {
+        let issuer = TokenIssuer::new(private_key());
+        let token = issuer.issue_paseto(test_token(), b"", b"tenant-1").unwrap();
+        let validate = |implicit_assertion: &[u8]| -> ValidateResult {
+            let config = ValidationConfig {
+                implicit_assertion: implicit_assertion.to_vec(),
+                ..Default::default()
+            };
+            make_validator().validate_paseto_config(&token, &config)
+        };
+        let x = validate(b"tenant-1");
+        assert!(x.is_ok());
+        let x = validate(b"tenant-2");
+        assert_auth_error!(x, SignatureVerificationFail);
+        let x = validate(b"");
+        assert_auth_error!(x, SignatureVerificationFail);
+    }
+
+    #[test]
+    fn key_id_in_footer() {
+        let issuer = TokenIssuer::with_key_id("k1", private_key());
+        let token = issuer.issue_paseto(test_token(), b"", b"").unwrap();
+        assert_eq!(paseto_footer(&token).unwrap(), br#"{"kid":"k1"}"#);
+
+        let mut key_ring = KeyRing::new();
+        key_ring.insert(
+            "k1",
+            PublicKey::from_base64(&get_test_public_key()).unwrap(),
+        );
+        let x: ValidateResult = TokenValidator::with_key_ring(key_ring).validate_paseto(&token);
+        assert!(x.is_ok());
+        let x: ValidateResult = make_validator().validate_paseto(&token);
+        assert_auth_error!(x, UnknownSigningKey);
+
+        // custom footer keeps the key id
+        let token = issuer
+            .issue_paseto(test_token(), br#"{"tenant":"t1"}"#, b"")
+            .unwrap();
+        assert_eq!(
+            paseto_footer(&token).unwrap(),
+            br#"{"kid":"k1","tenant":"t1"}"#
+        );
+        let token = issuer
+            .issue_paseto(test_token(), br#"{"kid":"k1"}"#, b"")
+            .unwrap();
+        assert_eq!(paseto_footer(&token).unwrap(), br#"{"kid":"k1"}"#);
+        for footer in [&b"footer"[..], b"[]", br#"{"kid":"k2"}"#] {
+            assert!(issuer.issue_paseto(test_token(), footer, b"").is_none());
+        }
+    }
+
+    #[test]
+    fn reject_non_ed25519_key() {
+        let key = crate::crypto::tests::get_test_ecdsa_key(Algorithm::EcdsaP256Sha256);
+        assert!(TokenIssuer::new(key)
+            .issue_paseto(test_token(), b"", b"")
+            .is_none());
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Claims of a signed access token which identify it for revocation
+#[derive(Debug, Clone, Copy)]
+pub struct RevocationQuery<'a> {
+    pub token_id: Option<&'a str>,
+    pub subject: Option<&'a str>,
+    pub issued_at: Option<SystemTime>,
+}
+
+/// Source of revoked tokens, consulted by `TokenValidator` after the token is verified
+pub trait RevocationStore: Send + Sync {
+    fn is_revoked(&self, query: &RevocationQuery<'_>) -> bool;
+}
+
+/// Set of revocation rules
+///
+/// A token is revoked if its token id is revoked, its subject is revoked, or it was issued
+/// before a cutoff. Tokens without `issued_at` claim are considered revoked by any cutoff
+/// that applies to them.
+#[derive(Debug, Clone, Default)]
+pub struct RevocationList {
+    token_ids: HashSet<String>,
+    /// Subject to cutoff, `None` revokes every token of the subject
+    subjects: HashMap<String, Option<SystemTime>>,
+    issued_before: Option<SystemTime>,
+}
+
+impl RevocationList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn revoke_token_id(&mut self, token_id: impl Into<String>) {
+        self.token_ids.insert(token_id.into());
+    }
+
+    pub fn revoke_subject(&mut self, subject: impl Into<String>) {
+        self.subjects.insert(subject.into(), None);
+    }
+
+    /// Revoke tokens of the subject which were issued before `time`
+    pub fn revoke_subject_issued_before(&mut self, subject: impl Into<String>, time: SystemTime) {
+        self.subjects.insert(subject.into(), Some(time));
+    }
+
+    /// Revoke every token issued before `time`
+    pub fn revoke_issued_before(&mut self, time: SystemTime) {
+        self.issued_before = Some(self.issued_before.map_or(time, |t| t.max(time)));
+    }
+
+    /// Parse revocation list from text, one rule per line
+    ///
+    /// ```text
+    /// # comment
+    /// jti <token id>
+    /// sub <subject>
+    /// sub <subject> <unix seconds>
+    /// iat <unix seconds>
+    /// ```
+    ///
+    /// `sub` with time revokes tokens of the subject issued before that time,
+    /// `iat` revokes every token issued before that time.
+    pub fn parse(input: &str) -> Result<Self, ParseRevocationListError> {
+        let mut list = Self::new();
+        for (index, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = || ParseRevocationListError { line: index + 1 };
+            let parse_time = |s: &str| {
+                s.parse()
+                    .ok()
+                    .and_then(|secs| UNIX_EPOCH.checked_add(Duration::from_secs(secs)))
+                    .ok_or_else(error)
+            };
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some("jti"), Some(token_id), None, None) => list.revoke_token_id(token_id),
+                (Some("sub"), Some(subject), None, None) => list.revoke_subject(subject),
+                (Some("sub"), Some(subject), Some(time), None) => {
+                    list.revoke_subject_issued_before(subject, parse_time(time)?)
+                }
+                (Some("iat"), Some(time), None, None) => {
+                    list.revoke_issued_before(parse_time(time)?)
+                }
+                _ => return Err(error()),
+            }
+        }
+        Ok(list)
+    }
+}
+
+impl RevocationStore for RevocationList {
+    fn is_revoked(&self, query: &RevocationQuery<'_>) -> bool {
+        let issued_before = |cutoff: SystemTime| query.issued_at.is_none_or(|t| t < cutoff);
+        if query
+            .token_id
+            .is_some_and(|token_id| self.token_ids.contains(token_id))
+        {
+            return true;
+        }
+        if let Some(cutoff) = query.subject.and_then(|subject| self.subjects.get(subject)) {
+            if cutoff.is_none_or(issued_before) {
+                return true;
+            }
+        }
+        self.issued_before.is_some_and(issued_before)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseRevocationListError {
+    /// One-based line number of invalid rule
+    pub line: usize,
+}
+
+impl Display for ParseRevocationListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid revocation rule at line {}", self.line)
+    }
+}
+
+impl std::error::Error for ParseRevocationListError {}
+
+/// Revocation list kept in memory which can be updated while validator is in use
+#[derive(Debug, Default)]
+pub struct InMemoryRevocationStore(RwLock<RevocationList>);
+
+impl InMemoryRevocationStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Modify revocation rules
+    pub fn update<F: FnOnce(&mut RevocationList)>(&self, f: F) {
+        f(&mut self.0.write().expect("poisoned revocation list"))
+    }
+}
+
+impl From<RevocationList> for InMemoryRevocationStore {
+    fn from(list: RevocationList) -> Self {
+        Self(RwLock::new(list))
+    }
+}
+
+impl RevocationStore for InMemoryRevocationStore {
+    fn is_revoked(&self, query: &RevocationQuery<'_>) -> bool {
+        self.0
+            .read()
+            .expect("poisoned revocation list")
+            .is_revoked(query)
+    }
+}
+
+/// Revocation list loaded from a file in [`RevocationList::parse`] format
+#[derive(Debug)]
+pub struct FileRevocationStore {
+    path: PathBuf,
+    list: RwLock<RevocationList>,
+}
+
+impl FileRevocationStore {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let list = RwLock::new(read_revocation_list(&path)?);
+        Ok(Self { path, list })
+    }
+
+    /// Read the file again, current rules are kept if the file cannot be read or parsed
+    pub fn reload(&self) -> io::Result<()> {
+        let list = read_revocation_list(&self.path)?;
+        *self.list.write().expect("poisoned revocation list") = list;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+fn read_revocation_list(path: &Path) -> io::Result<RevocationList> {
+    let content = fs::read_to_string(path)?;
+    RevocationList::parse(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+impl RevocationStore for FileRevocationStore {
+    fn is_revoked(&self, query: &RevocationQuery<'_>) -> bool {
+        self.list
+            .read()
+            .expect("poisoned revocation list")
+            .is_revoked(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(secs: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    fn query<'a>(
+        token_id: Option<&'a str>,
+        subject: Option<&'a str>,
+        issued_at: Option<u64>,
+    ) -> RevocationQuery<'a> {
+        RevocationQuery {
+            token_id,
+            subject,
+            issued_at: issued_at.map(time),
+        }
+    }
+
+    #[test]
+    fn revocation_rules() {
+        let mut list = RevocationList::new();
+        list.revoke_token_id("t1");
+        list.revoke_subject("alice");
+        list.revoke_subject_issued_before("bob", time(100));
+
+        assert!(list.is_revoked(&query(Some("t1"), None, None)));
+        assert!(!list.is_revoked(&query(Some("t2"), None, None)));
+        assert!(list.is_revoked(&query(None, Some("alice"), Some(200))));
+        assert!(list.is_revoked(&query(None, Some("bob"), Some(99))));
+        assert!(list.is_revoked(&query(None, Some("bob"), None)));
+        assert!(!list.is_revoked(&query(None, Some("bob"), Some(100))));
+        assert!(!list.is_revoked(&query(None, Some("carol"), Some(0))));
+
+        list.revoke_issued_before(time(50));
+        assert!(list.is_revoked(&query(None, Some("carol"), Some(49))));
+        assert!(!list.is_revoked(&query(None, Some("carol"), Some(50))));
+    }
+
+    #[test]
+    fn parse_revocation_list() {
+        let list =
+            RevocationList::parse("# comment\n\njti t1\nsub alice\nsub bob 100\niat 50\n").unwrap();
+        assert!(list.is_revoked(&query(Some("t1"), None, Some(60))));
+        assert!(list.is_revoked(&query(None, Some("alice"), Some(60))));
+        assert!(list.is_revoked(&query(None, Some("bob"), Some(60))));
+        assert!(list.is_revoked(&query(None, None, Some(49))));
+        assert!(!list.is_revoked(&query(None, None, Some(60))));
+
+        let err = RevocationList::parse("jti t1\nsub\n").unwrap_err();
+        assert_eq!(err.line, 2);
+        let err = RevocationList::parse("iat yesterday").unwrap_err();
+        assert_eq!(err.line, 1);
+        let err = RevocationList::parse("jti t1\niat 18446744073709551615").unwrap_err();
+        assert_eq!(err.line, 2);
+    }
+
+    #[test]
+    fn reload_file_revocation_store() {
+        let path = std::env::temp_dir().join(format!("tokidator-revoked-{}", std::process::id()));
+        fs::write(&path, "jti t1\n").unwrap();
+        let store = FileRevocationStore::open(&path).unwrap();
+        assert!(store.is_revoked(&query(Some("t1"), None, None)));
+        assert!(!store.is_revoked(&query(Some("t2"), None, None)));
+
+        fs::write(&path, "jti t2\n").unwrap();
+        store.reload().unwrap();
+        assert!(!store.is_revoked(&query(Some("t1"), None, None)));
+        assert!(store.is_revoked(&query(Some("t2"), None, None)));
+
+        fs::write(&path, "bad rule\n").unwrap();
+        assert!(store.reload().is_err());
+        assert!(store.is_revoked(&query(Some("t2"), None, None)));
+        fs::remove_file(&path).unwrap();
+    }
+}
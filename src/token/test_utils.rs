@@ -1,14 +1,35 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use protobuf::Message;
 
+use crate::crypto::tests::{get_test_private_key, get_test_public_key};
+use crate::crypto::{PrivateKey, PublicKey};
 use crate::rbac::test_helpers::TestPermission;
 use crate::rbac::PermissionSet;
 
-use super::AccessToken;
+use super::{AccessToken, IssueClaims, TokenClaims, TokenIssuer, TokenValidator, ValidationError};
+
+macro_rules! assert_auth_error {
+    ($exp:expr, $err:path) => {
+        assert!(
+            matches!($exp.as_ref().map_err(|e| e.kind()), Err($err)),
+            concat!("Expect Err(", stringify!($err), ") but found {:?}"),
+            $exp
+        );
+    };
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TestAccessToken {
     permissions: PermissionSet<TestPermission>,
     expired: bool,
+    issued_at: Option<u64>,
+    not_before: Option<u64>,
+    expires_at: Option<u64>,
+    issuer: Option<String>,
+    audience: Vec<String>,
+    subject: Option<String>,
+    token_id: Option<String>,
 }
 
 impl TestAccessToken {
@@ -16,8 +37,89 @@ impl TestAccessToken {
         Self {
             permissions,
             expired,
+            issued_at: None,
+            not_before: None,
+            expires_at: None,
+            issuer: None,
+            audience: Vec::new(),
+            subject: None,
+            token_id: None,
         }
     }
+
+    pub fn with_not_before(mut self, time: SystemTime) -> Self {
+        self.not_before = Some(unix_seconds(time));
+        self
+    }
+
+    pub fn with_expires_at(mut self, time: SystemTime) -> Self {
+        self.expires_at = Some(unix_seconds(time));
+        self
+    }
+
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_owned());
+        self
+    }
+
+    pub fn with_audience(mut self, audience: &[&str]) -> Self {
+        self.audience = audience.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn with_subject(mut self, subject: &str) -> Self {
+        self.subject = Some(subject.to_owned());
+        self
+    }
+
+    pub fn with_token_id(mut self, token_id: &str) -> Self {
+        self.token_id = Some(token_id.to_owned());
+        self
+    }
+}
+
+/// Requirement of `Authorized` extractors of framework integrations
+#[cfg(any(feature = "actix-web", feature = "axum"))]
+pub struct CanUsePermission2;
+
+#[cfg(any(feature = "actix-web", feature = "axum"))]
+impl crate::rbac::Requirement for CanUsePermission2 {
+    type Permission = TestPermission;
+
+    fn predicate() -> crate::rbac::Predicate<Self::Permission> {
+        crate::rbac::Predicate::contains(TestPermission::Permission2)
+    }
+}
+
+pub type ValidateResult = Result<TestAccessToken, ValidationError<()>>;
+
+/// Signing key of the test identity server
+pub fn private_key() -> PrivateKey {
+    PrivateKey::from_base64(&get_test_private_key()).unwrap()
+}
+
+/// Validator which trusts [`private_key`]
+pub fn make_validator() -> TokenValidator {
+    TokenValidator::new(PublicKey::from_base64(&get_test_public_key()).unwrap())
+}
+
+/// Issue token which is valid for [`make_validator`]
+pub fn issue(token: TestAccessToken) -> String {
+    TokenIssuer::new(private_key()).issue(token)
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .expect("Time before unix epoch")
+        .as_secs()
+}
+
+fn from_unix_seconds(secs: u64) -> Option<SystemTime> {
+    (secs != 0).then(|| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn non_empty(s: String) -> Option<String> {
+    (!s.is_empty()).then_some(s)
 }
 
 impl AccessToken for TestAccessToken {
@@ -26,9 +128,18 @@ impl AccessToken for TestAccessToken {
 
     fn from_bytes(buf: &[u8]) -> Result<Self, Self::ParseError> {
         let token = crate::protos::token::TestAccessToken::parse_from_bytes(buf).map_err(drop)?;
-        let ps = PermissionSet::parse_from_bytes(token.permissions.as_slice())
-            .expect("Bad encoded test permissions");
-        Ok(Self::new(ps, token.expired))
+        let ps = PermissionSet::parse_from_bytes_lossy(token.permissions.as_slice());
+        Ok(Self {
+            permissions: ps,
+            expired: token.expired,
+            issued_at: (token.issued_at != 0).then_some(token.issued_at),
+            not_before: (token.not_before != 0).then_some(token.not_before),
+            expires_at: (token.expires_at != 0).then_some(token.expires_at),
+            issuer: non_empty(token.issuer),
+            audience: token.audience,
+            subject: non_empty(token.subject),
+            token_id: non_empty(token.token_id),
+        })
     }
 
     fn to_bytes(&self) -> Vec<u8> {
@@ -36,16 +147,72 @@ impl AccessToken for TestAccessToken {
         let mut builder = crate::protos::token::TestAccessToken::new();
         builder.permissions = permissions;
         builder.expired = self.expired;
+        builder.issued_at = self.issued_at.unwrap_or_default();
+        builder.not_before = self.not_before.unwrap_or_default();
+        builder.expires_at = self.expires_at.unwrap_or_default();
+        builder.issuer = self.issuer.clone().unwrap_or_default();
+        builder.audience = self.audience.clone();
+        builder.subject = self.subject.clone().unwrap_or_default();
+        builder.token_id = self.token_id.clone().unwrap_or_default();
         builder
             .write_to_bytes()
             .expect("Fail build bytes from test permission")
     }
 
-    fn is_expired(&self) -> bool {
-        self.expired
+    fn from_claims(claims: TokenClaims<Self::Permission>) -> Option<Self> {
+        Some(Self {
+            permissions: claims.permissions,
+            expired: false,
+            issued_at: claims.issued_at.map(unix_seconds),
+            not_before: claims.not_before.map(unix_seconds),
+            expires_at: claims.expires_at.map(unix_seconds),
+            issuer: claims.issuer,
+            audience: claims.audience,
+            subject: claims.subject,
+            token_id: claims.token_id,
+        })
     }
 
     fn permissions(&self) -> &PermissionSet<Self::Permission> {
         &self.permissions
     }
+
+    fn is_expired(&self) -> bool {
+        self.expired
+    }
+
+    fn issued_at(&self) -> Option<SystemTime> {
+        self.issued_at.and_then(from_unix_seconds)
+    }
+
+    fn not_before(&self) -> Option<SystemTime> {
+        self.not_before.and_then(from_unix_seconds)
+    }
+
+    fn expires_at(&self) -> Option<SystemTime> {
+        self.expires_at.and_then(from_unix_seconds)
+    }
+
+    fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    fn audience(&self) -> &[String] {
+        &self.audience
+    }
+
+    fn subject(&self) -> Option<&str> {
+        self.subject.as_deref()
+    }
+
+    fn token_id(&self) -> Option<&str> {
+        self.token_id.as_deref()
+    }
+
+    fn stamp_claims(&mut self, claims: &IssueClaims) {
+        self.issued_at = Some(unix_seconds(claims.issued_at));
+        if let Some(expires_at) = claims.expires_at {
+            self.expires_at = Some(unix_seconds(expires_at));
+        }
+    }
 }
@@ -1,19 +1,101 @@
-use crate::rbac::{Permission, PermissionSet, Predicate};
+use std::time::SystemTime;
+
+use crate::rbac::{Decision, Permission, PermissionSet, Predicate};
+
+use super::{IssueClaims, TokenClaims};
 
 pub trait AccessToken: Sized {
     type Permission: Permission;
     type ParseError;
 
+    /// Parse access token from payload
+    ///
+    /// Decode permissions with [`PermissionSet::parse_from_bytes_lossy`] to let
+    /// `UnknownPermissionPolicy` of `TokenValidator` decide about unknown permissions.
+    /// With [`PermissionSet::parse_from_bytes`] they are handled here and the policy has no
+    /// effect.
     fn from_bytes(buf: &[u8]) -> Result<Self, Self::ParseError>;
     fn to_bytes(&self) -> Vec<u8>;
 
-    fn is_expired(&self) -> bool;
+    /// Build access token from claims of JWT or PASETO token, as validated by
+    /// `TokenValidator::validate_jwt` and `validate_paseto`
+    ///
+    /// Return `None` to reject the token. Tokens which are not used in these formats can keep
+    /// the default implementation, which rejects them.
+    fn from_claims(_claims: TokenClaims<Self::Permission>) -> Option<Self> {
+        None
+    }
+
+    /// Permissions of the token, including bit indexes of unknown permissions
+    ///
+    /// `TokenValidator` finds unknown permissions through
+    /// [`PermissionSet::unknown_bits`], which is only filled by lossy parsing.
     fn permissions(&self) -> &PermissionSet<Self::Permission>;
 
+    /// Expiration check of the implementor, in addition to `expires_at` claim
+    ///
+    /// `TokenValidator` rejects the token if this returns `true` or if `expires_at` has passed,
+    /// taking leeway and clock of `ValidationConfig` into account. Tokens which only carry
+    /// `expires_at` claim can keep the default implementation.
+    fn is_expired(&self) -> bool {
+        false
+    }
+
+    /// Time at which the token was issued (`iat`)
+    fn issued_at(&self) -> Option<SystemTime> {
+        None
+    }
+
+    /// Time before which the token must not be accepted (`nbf`)
+    fn not_before(&self) -> Option<SystemTime> {
+        None
+    }
+
+    /// Time at which the token expires (`exp`)
+    fn expires_at(&self) -> Option<SystemTime> {
+        None
+    }
+
+    /// Identity server which issued the token (`iss`)
+    fn issuer(&self) -> Option<&str> {
+        None
+    }
+
+    /// Recipients that the token is intended for (`aud`)
+    fn audience(&self) -> &[String] {
+        &[]
+    }
+
+    /// Principal that is the subject of the token (`sub`)
+    fn subject(&self) -> Option<&str> {
+        None
+    }
+
+    /// Unique identifier of the token (`jti`)
+    fn token_id(&self) -> Option<&str> {
+        None
+    }
+
+    /// Record issuance claims, called by `TokenIssuer` before the token is signed
+    ///
+    /// Tokens that do not carry any claim can keep the default no-op implementation.
+    fn stamp_claims(&mut self, _claims: &IssueClaims) {}
+
+    /// Check predicate against permissions of the token
+    #[deprecated(note = "decision is not audited, use `TokenValidator::authorize` instead")]
     fn is_authorized<P>(&self, predicate: P) -> bool
     where
         P: AsRef<Predicate<Self::Permission>>,
     {
         predicate.as_ref().satisfy(self.permissions())
     }
+
+    /// Check predicate against permissions of the token, explaining a denial
+    #[deprecated(note = "decision is not audited, use `TokenValidator::authorize` instead")]
+    fn authorize<P>(&self, predicate: P) -> Decision<Self::Permission>
+    where
+        P: AsRef<Predicate<Self::Permission>>,
+    {
+        predicate.as_ref().evaluate(self.permissions())
+    }
 }
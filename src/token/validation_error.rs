@@ -0,0 +1,160 @@
+use std::error::Error as StdError;
+use std::fmt::{self, Debug, Display};
+use std::time::SystemTime;
+
+use crate::error::Error;
+
+use super::AccessToken;
+
+/// Error returned by `TokenValidator`
+///
+/// Besides the coarse [`Error`] kind, it records the stage at which validation failed,
+/// the parse error of the access token and whatever is known about the token at that stage.
+#[derive(Debug)]
+pub struct ValidationError<E> {
+    kind: Error,
+    stage: ValidationStage,
+    parse_error: Option<E>,
+    metadata: Box<TokenMetadata>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationStage {
+    /// Decoding signed message
+    Decode,
+    /// Looking up encryption key and decrypting encrypted token
+    Decryption,
+    /// Looking up signing key and verifying signature
+    Signature,
+    /// Parsing access token from payload
+    Parse,
+    /// Checking token claims
+    Claims,
+    /// Checking revocation store
+    Revocation,
+}
+
+/// Information about the token which failed validation
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct TokenMetadata {
+    pub key_id: Option<String>,
+    pub issued_at: Option<SystemTime>,
+    pub not_before: Option<SystemTime>,
+    pub expires_at: Option<SystemTime>,
+    pub issuer: Option<String>,
+    pub subject: Option<String>,
+    pub token_id: Option<String>,
+    /// Bit indexes of permissions unknown to this server
+    pub unknown_permissions: Vec<usize>,
+    /// Index of the failed block of a delegated token, the first block is issued by the
+    /// identity server and only that one is signed by `key_id`
+    pub delegation_block: Option<usize>,
+}
+
+impl TokenMetadata {
+    pub(crate) fn with_key_id(key_id: Option<&str>) -> Self {
+        Self {
+            key_id: key_id.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn with_claims<A: AccessToken>(self, access_token: &A) -> Self {
+        Self {
+            issued_at: access_token.issued_at(),
+            not_before: access_token.not_before(),
+            expires_at: access_token.expires_at(),
+            issuer: access_token.issuer().map(String::from),
+            subject: access_token.subject().map(String::from),
+            token_id: access_token.token_id().map(String::from),
+            ..self
+        }
+    }
+}
+
+impl<E> ValidationError<E> {
+    pub(crate) fn new(kind: Error, stage: ValidationStage, metadata: TokenMetadata) -> Self {
+        Self {
+            kind,
+            stage,
+            parse_error: None,
+            metadata: Box::new(metadata),
+        }
+    }
+
+    pub(crate) fn parse(parse_error: E, metadata: TokenMetadata) -> Self {
+        Self {
+            parse_error: Some(parse_error),
+            ..Self::new(Error::InvalidAccessToken, ValidationStage::Parse, metadata)
+        }
+    }
+
+    pub(crate) fn in_delegation_block(mut self, index: usize) -> Self {
+        self.metadata.delegation_block = Some(index);
+        self
+    }
+
+    pub fn kind(&self) -> Error {
+        self.kind
+    }
+
+    pub fn stage(&self) -> ValidationStage {
+        self.stage
+    }
+
+    pub fn parse_error(&self) -> Option<&E> {
+        self.parse_error.as_ref()
+    }
+
+    pub fn into_parse_error(self) -> Option<E> {
+        self.parse_error
+    }
+
+    pub fn metadata(&self) -> &TokenMetadata {
+        &self.metadata
+    }
+}
+
+impl Display for ValidationStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use ValidationStage::*;
+        match *self {
+            Decode => f.write_str("decode"),
+            Decryption => f.write_str("decryption"),
+            Signature => f.write_str("signature"),
+            Parse => f.write_str("parse"),
+            Claims => f.write_str("claims"),
+            Revocation => f.write_str("revocation"),
+        }
+    }
+}
+
+impl<E: Debug> Display for ValidationError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {} stage", self.kind, self.stage)?;
+        if let Some(key_id) = &self.metadata.key_id {
+            write!(f, " (key id: {})", key_id)?;
+        }
+        if let Some(index) = self.metadata.delegation_block {
+            write!(f, " (delegation block: {})", index)?;
+        }
+        if let Some(parse_error) = &self.parse_error {
+            write!(f, ": {:?}", parse_error)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: StdError + 'static> StdError for ValidationError<E> {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.parse_error.as_ref().map(|e| e as _)
+    }
+}
+
+impl<E> From<ValidationError<E>> for Error {
+    fn from(error: ValidationError<E>) -> Self {
+        error.kind
+    }
+}
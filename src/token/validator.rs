@@ -1,95 +1,649 @@
-use crate::crypto::{PublicKey, SignedMessage};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde_json::json;
+use tracing::warn;
+
+use crate::audit::AuditAction::{Authorization, Validation};
+use crate::audit::{AuditEvent, AuditSink};
+use crate::crypto::{Algorithm, EncryptedMessage, EncryptionKey, HmacKey, SignedMessage, Verifier};
 use crate::error::Error::{self, *};
+use crate::rbac::{Decision, Denial, Predicate};
+
+use super::claims::TimeFormat;
+use super::delegation::{self, DelegatedToken};
+use super::{
+    jwt, paseto, AccessToken, KeyRing, RevocationQuery, RevocationStore, TokenClaims,
+    TokenMetadata, ValidationError, ValidationStage,
+};
+
+/// Validator of tokens signed by keys `K`
+///
+/// `TokenValidator` verifies public-key signatures with a [`KeyRing`],
+/// [`HmacTokenValidator`] verifies HMAC tags with a shared secret. Each of them rejects
+/// tokens signed the other way.
+pub struct TokenValidator<K = KeyRing> {
+    keys: K,
+    encryption_keys: BTreeMap<String, EncryptionKey>,
+    revocation_store: Option<Arc<dyn RevocationStore>>,
+    audit_sink: Option<Arc<dyn AuditSink>>,
+}
+
+/// Validator of tokens signed with a secret shared between services
+pub type HmacTokenValidator = TokenValidator<HmacKey>;
+
+/// Keys which verify signatures of tokens, [`KeyRing`] or [`HmacKey`]
+///
+/// The trait is sealed, the kind of accepted signatures is fixed by the validator type.
+pub trait VerificationKeys: sealed::Sealed {
+    /// Whether the signature is valid, `None` if no key fits the message
+    fn verify(&self, signed_message: &SignedMessage, now: SystemTime) -> Option<bool>;
+
+    /// Whether the algorithm is accepted when
+    /// [`allowed_algorithms`](ValidationConfig::allowed_algorithms) is not set
+    fn is_default_algorithm(&self, algorithm: Algorithm) -> bool;
+}
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for super::KeyRing {}
+    impl Sealed for super::HmacKey {}
+}
+
+impl VerificationKeys for KeyRing {
+    fn verify(&self, signed_message: &SignedMessage, now: SystemTime) -> Option<bool> {
+        if signed_message.algorithm().is_symmetric() {
+            return None;
+        }
+        let public_key = self.find(signed_message.key_id(), now)?;
+        Some(signed_message.verify(public_key))
+    }
 
-use super::AccessToken;
+    fn is_default_algorithm(&self, algorithm: Algorithm) -> bool {
+        algorithm == Algorithm::Ed25519
+    }
+}
+
+impl VerificationKeys for HmacKey {
+    fn verify(&self, signed_message: &SignedMessage, _now: SystemTime) -> Option<bool> {
+        if !signed_message.algorithm().is_symmetric() {
+            return None;
+        }
+        Some(signed_message.verify_hmac(self))
+    }
 
-pub struct TokenValidator {
-    public_key: PublicKey,
+    fn is_default_algorithm(&self, algorithm: Algorithm) -> bool {
+        algorithm == self.algorithm()
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct ValidationConfig {
+    /// Check `issued_at`, `not_before` and `expires_at` claims
     pub check_expiration: bool,
+    /// Allowed clock skew between identity server and this server
+    pub leeway: Duration,
+    /// Source of current time, tests can replace it to validate at a fixed time
+    pub clock: fn() -> SystemTime,
+    /// What to do with permissions unknown to this server
+    pub unknown_permissions: UnknownPermissionPolicy,
+    /// Required `issuer` claim
+    pub issuer: Option<String>,
+    /// Accepted audiences, token must be intended for at least one of them if not empty
+    pub audience: Vec<String>,
+    /// Accepted signature algorithms, tokens signed with any other algorithm are rejected
+    /// even if the key ring holds a matching key
+    ///
+    /// `None` accepts Ed25519 for `TokenValidator` and the algorithm of the shared secret for
+    /// `HmacTokenValidator`.
+    pub allowed_algorithms: Option<Vec<Algorithm>>,
+    /// Implicit assertion of PASETO tokens, it must be the one the token was issued with
+    pub implicit_assertion: Vec<u8>,
+    /// Also accept tokens in the unversioned format, only for migrating existing deployments
+    pub accept_legacy_format: bool,
+}
+
+/// Policy for tokens carrying permissions which this server does not know
+///
+/// Identity server may use a newer version of permission library than this server.
+/// Unknown permissions never satisfy any predicate, their bit indexes are available from
+/// `PermissionSet::unknown_bits` of the validated token.
+///
+/// The policy only sees unknown permissions if `AccessToken::from_bytes` decodes permissions
+/// with `PermissionSet::parse_from_bytes_lossy`. With the strict `parse_from_bytes`,
+/// unknown permissions are handled by `from_bytes` and the policy has no effect.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnknownPermissionPolicy {
+    Reject,
+    Ignore,
+    /// Accept the token and log unknown permissions as a warning
+    Report,
 }
 
 impl Default for ValidationConfig {
     fn default() -> Self {
         Self {
             check_expiration: true,
+            leeway: Duration::ZERO,
+            clock: SystemTime::now,
+            unknown_permissions: UnknownPermissionPolicy::Report,
+            issuer: None,
+            audience: Vec::new(),
+            allowed_algorithms: None,
+            implicit_assertion: Vec::new(),
+            accept_legacy_format: false,
         }
     }
 }
 
 impl TokenValidator {
-    pub fn new(public_key: PublicKey) -> Self {
-        Self { public_key }
+    pub fn new(public_key: impl Verifier + 'static) -> Self {
+        let mut key_ring = KeyRing::new();
+        key_ring.set_default_key(Some(Arc::new(public_key)));
+        Self::with_key_ring(key_ring)
     }
 
-    pub fn validate<A: AccessToken, T: AsRef<[u8]>>(&self, token: T) -> Result<A, Error> {
-        self.validate_config(
-            token,
-            ValidationConfig {
-                check_expiration: true,
-            },
-        )
+    pub fn with_key_ring(key_ring: KeyRing) -> Self {
+        Self::with_keys(key_ring)
+    }
+
+    pub fn key_ring(&self) -> &KeyRing {
+        &self.keys
+    }
+
+    pub fn key_ring_mut(&mut self) -> &mut KeyRing {
+        &mut self.keys
+    }
+}
+
+impl HmacTokenValidator {
+    /// Create validator for tokens signed with a secret shared between services
+    pub fn with_hmac_key(hmac_key: HmacKey) -> Self {
+        Self::with_keys(hmac_key)
+    }
+}
+
+impl<K: VerificationKeys> TokenValidator<K> {
+    fn with_keys(keys: K) -> Self {
+        Self {
+            keys,
+            encryption_keys: BTreeMap::new(),
+            revocation_store: None,
+            audit_sink: None,
+        }
+    }
+
+    /// Trust key for decrypting encrypted tokens which name `key_id`
+    pub fn insert_encryption_key(&mut self, key_id: impl Into<String>, key: EncryptionKey) {
+        self.encryption_keys.insert(key_id.into(), key);
+    }
+
+    pub fn remove_encryption_key(&mut self, key_id: &str) -> Option<EncryptionKey> {
+        self.encryption_keys.remove(key_id)
+    }
+
+    /// Reject tokens revoked by the store, the store is consulted after every other check
+    pub fn set_revocation_store(&mut self, store: Option<Arc<dyn RevocationStore>>) {
+        self.revocation_store = store;
+    }
+
+    /// Record every validation and [`authorize`](Self::authorize) decision to the sink
+    pub fn set_audit_sink(&mut self, sink: Option<Arc<dyn AuditSink>>) {
+        self.audit_sink = sink;
+    }
+
+    pub fn validate<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.validate_config(token, &ValidationConfig::default())
     }
 
     pub fn validate_config<A: AccessToken, T: AsRef<[u8]>>(
         &self,
         token: T,
-        config: ValidationConfig,
-    ) -> Result<A, Error> {
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.audited(config.clock, self.validate_signed_token(token, config))
+    }
+
+    fn validate_signed_token<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        use ValidationStage::*;
+
+        let now = (config.clock)();
         // 1. decode signed message
-        let signed_message = SignedMessage::decode(token).ok_or(InvalidSignedMessage)?;
-        // 2. check if it is generated by trusted identity server
-        if !signed_message.verify(&self.public_key) {
-            return Err(SignatureVerificationFail);
-        }
+        let token = token.as_ref();
+        let signed_message = SignedMessage::decode(token)
+            .or_else(|| {
+                config
+                    .accept_legacy_format
+                    .then(|| SignedMessage::decode_legacy(token))
+                    .flatten()
+            })
+            .ok_or_else(|| {
+                ValidationError::new(InvalidSignedMessage, Decode, TokenMetadata::default())
+            })?;
+        // 2. check if it is generated by trusted identity server with allowed algorithm
+        self.verify_signature(&signed_message, config, now)?;
         // 3. extract access token from payload
-        let access_token =
-            A::from_bytes(signed_message.message()).map_err(|_| InvalidAccessToken)?;
+        let access_token = A::from_bytes(signed_message.message()).map_err(|e| {
+            ValidationError::parse(e, TokenMetadata::with_key_id(signed_message.key_id()))
+        })?;
+        self.check_access_token(access_token, signed_message.key_id(), config, now)
+    }
+
+    /// Decrypt and validate token, as issued by
+    /// [`TokenIssuer::issue_encrypted`](super::TokenIssuer::issue_encrypted)
+    pub fn validate_encrypted<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.validate_encrypted_config(token, &ValidationConfig::default())
+    }
+
+    pub fn validate_encrypted_config<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.audited(config.clock, self.validate_encrypted_token(token, config))
+    }
+
+    fn validate_encrypted_token<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        use ValidationStage::*;
+
+        // 1. decode encrypted message
+        let encrypted_message = EncryptedMessage::decode(token).ok_or_else(|| {
+            ValidationError::new(InvalidSignedMessage, Decode, TokenMetadata::default())
+        })?;
+        let metadata = || TokenMetadata::with_key_id(Some(encrypted_message.key_id()));
+        // 2. decrypt with the key named by the token
+        let encryption_key = self
+            .encryption_keys
+            .get(encrypted_message.key_id())
+            .ok_or_else(|| ValidationError::new(UnknownEncryptionKey, Decryption, metadata()))?;
+        let signed_token = encrypted_message
+            .open(encryption_key)
+            .ok_or_else(|| ValidationError::new(DecryptionFail, Decryption, metadata()))?;
+        // 3. validate signed token inside
+        self.validate_signed_token(signed_token, config)
+    }
+
+    /// Validate JWT in JWS compact serialization, as issued by
+    /// [`TokenIssuer::issue_jwt`](super::TokenIssuer::issue_jwt)
+    pub fn validate_jwt<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.validate_jwt_config(token, &ValidationConfig::default())
+    }
+
+    pub fn validate_jwt_config<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.audited(config.clock, self.validate_jwt_token(token, config))
+    }
+
+    fn validate_jwt_token<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        use ValidationStage::*;
+
+        let now = (config.clock)();
+        // 1. decode JWS compact serialization
+        let signed_message = jwt::decode(token.as_ref()).ok_or_else(|| {
+            ValidationError::new(InvalidSignedMessage, Decode, TokenMetadata::default())
+        })?;
+        let metadata = || TokenMetadata::with_key_id(signed_message.key_id());
+        // 2. check if it is generated by trusted identity server with allowed algorithm
+        self.verify_signature(&signed_message, config, now)?;
+        // 3. build access token from claims
+        let access_token = jwt::payload(signed_message.message())
+            .and_then(|payload| TokenClaims::parse(&payload, TimeFormat::NumericDate))
+            .and_then(A::from_claims)
+            .ok_or_else(|| ValidationError::new(InvalidAccessToken, Parse, metadata()))?;
+        self.check_access_token(access_token, signed_message.key_id(), config, now)
+    }
+
+    /// Validate PASETO v4.public token, as issued by
+    /// [`TokenIssuer::issue_paseto`](super::TokenIssuer::issue_paseto)
+    pub fn validate_paseto<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.validate_paseto_config(token, &ValidationConfig::default())
+    }
+
+    pub fn validate_paseto_config<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.audited(config.clock, self.validate_paseto_token(token, config))
+    }
+
+    fn validate_paseto_token<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        use ValidationStage::*;
+
+        let now = (config.clock)();
+        // 1. decode PASETO v4.public token
+        let (signed_message, payload) = paseto::decode(token.as_ref(), &config.implicit_assertion)
+            .ok_or_else(|| {
+                ValidationError::new(InvalidSignedMessage, Decode, TokenMetadata::default())
+            })?;
+        // 2. check if it is generated by trusted identity server with allowed algorithm
+        self.verify_signature(&signed_message, config, now)?;
+        // 3. build access token from claims
+        let access_token = TokenClaims::parse(&payload, TimeFormat::DateTime)
+            .and_then(A::from_claims)
+            .ok_or_else(|| {
+                let metadata = TokenMetadata::with_key_id(signed_message.key_id());
+                ValidationError::new(InvalidAccessToken, Parse, metadata)
+            })?;
+        self.check_access_token(access_token, signed_message.key_id(), config, now)
+    }
+
+    /// Validate delegated token, as issued by
+    /// [`TokenIssuer::issue_delegated`](super::TokenIssuer::issue_delegated) and attenuated
+    /// by [`attenuate`](super::attenuate)
+    ///
+    /// Every token of the chain is validated and must not exceed the previous one, the last
+    /// token is returned.
+    pub fn validate_delegated<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.validate_delegated_config(token, &ValidationConfig::default())
+    }
+
+    pub fn validate_delegated_config<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        self.audited(config.clock, self.validate_delegated_token(token, config))
+    }
+
+    fn validate_delegated_token<A: AccessToken, T: AsRef<[u8]>>(
+        &self,
+        token: T,
+        config: &ValidationConfig,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        use ValidationStage::*;
+
+        let now = (config.clock)();
+        // 1. decode chain of blocks
+        let delegated_token = DelegatedToken::decode(token.as_ref()).ok_or_else(|| {
+            ValidationError::new(InvalidSignedMessage, Decode, TokenMetadata::default())
+        })?;
+        // only the first block is signed by the key of the identity server
+        let key_id = |index| delegated_token.key_id().filter(|_| index == 0);
+        let metadata = |index| TokenMetadata {
+            delegation_block: Some(index),
+            ..TokenMetadata::with_key_id(key_id(index))
+        };
+        // 2. check that the first block is generated by trusted identity server and every
+        //    other block is signed by the key of the previous one
+        self.verify_signature(&delegated_token.root_signed_message(), config, now)
+            .map_err(|error| error.in_delegation_block(0))?;
+        if let Some(index) = delegated_token.unverified_block() {
+            let metadata = metadata(index);
+            return Err(ValidationError::new(
+                SignatureVerificationFail,
+                Signature,
+                metadata,
+            ));
+        }
+        // 3. extract access tokens, each one must be validated and narrower than its parent
+        let mut parent = None;
+        for (index, payload) in delegated_token.payloads().enumerate() {
+            let access_token =
+                A::from_bytes(payload).map_err(|e| ValidationError::parse(e, metadata(index)))?;
+            if parent
+                .as_ref()
+                .is_some_and(|parent| !delegation::is_attenuation(&access_token, parent))
+            {
+                let metadata = metadata(index).with_claims(&access_token);
+                return Err(ValidationError::new(InvalidAttenuation, Claims, metadata));
+            }
+            let access_token = self
+                .check_access_token(access_token, key_id(index), config, now)
+                .map_err(|error| error.in_delegation_block(index))?;
+            parent = Some(access_token);
+        }
+        Ok(parent.expect("delegated token has at least one block"))
+    }
+
+    /// Check predicate against validated token and record the decision to the audit sink
+    pub fn authorize<A, P>(&self, access_token: &A, predicate: P) -> Decision<A::Permission>
+    where
+        A: AccessToken,
+        P: AsRef<Predicate<A::Permission>>,
+    {
+        self.authorize_config(access_token, predicate, &ValidationConfig::default())
+    }
+
+    /// Like [`authorize`](Self::authorize), the decision is recorded at the time of
+    /// [`clock`](ValidationConfig::clock)
+    pub fn authorize_config<A, P>(
+        &self,
+        access_token: &A,
+        predicate: P,
+        config: &ValidationConfig,
+    ) -> Decision<A::Permission>
+    where
+        A: AccessToken,
+        P: AsRef<Predicate<A::Permission>>,
+    {
+        let predicate = predicate.as_ref();
+        let decision = predicate.evaluate(access_token.permissions());
+        if let Some(sink) = &self.audit_sink {
+            let now = (config.clock)();
+            let mut event = AuditEvent::new(Authorization, decision.is_granted(), now);
+            event.subject = access_token.subject().map(String::from);
+            event.token_id = access_token.token_id().map(String::from);
+            event.predicate = Some(predicate.to_discriminant_string());
+            event.reason = decision.denial().map(Denial::to_discriminant_json);
+            sink.record(&event);
+        }
+        decision
+    }
+
+    fn audited<A: AccessToken>(
+        &self,
+        clock: fn() -> SystemTime,
+        result: Result<A, ValidationError<A::ParseError>>,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        if let Some(sink) = &self.audit_sink {
+            let mut event = AuditEvent::new(Validation, result.is_ok(), clock());
+            match &result {
+                Ok(access_token) => {
+                    event.subject = access_token.subject().map(String::from);
+                    event.token_id = access_token.token_id().map(String::from);
+                }
+                Err(error) => {
+                    event.subject = error.metadata().subject.clone();
+                    event.token_id = error.metadata().token_id.clone();
+                    event.reason = Some(json!({
+                        "error": error.kind().to_string(),
+                        "stage": error.stage().to_string(),
+                    }));
+                }
+            }
+            sink.record(&event);
+        }
+        result
+    }
+
+    fn verify_signature<E>(
+        &self,
+        signed_message: &SignedMessage,
+        config: &ValidationConfig,
+        now: SystemTime,
+    ) -> Result<(), ValidationError<E>> {
+        let error = |kind| {
+            let metadata = TokenMetadata::with_key_id(signed_message.key_id());
+            ValidationError::new(kind, ValidationStage::Signature, metadata)
+        };
+        let algorithm = signed_message.algorithm();
+        let is_allowed = match &config.allowed_algorithms {
+            Some(allowed_algorithms) => allowed_algorithms.contains(&algorithm),
+            None => self.keys.is_default_algorithm(algorithm),
+        };
+        if !is_allowed {
+            return Err(error(DisallowedAlgorithm));
+        }
+        let verified = self
+            .keys
+            .verify(signed_message, now)
+            .ok_or_else(|| error(UnknownSigningKey))?;
+        if !verified {
+            return Err(error(SignatureVerificationFail));
+        }
+        Ok(())
+    }
+
+    fn check_access_token<A: AccessToken>(
+        &self,
+        access_token: A,
+        key_id: Option<&str>,
+        config: &ValidationConfig,
+        now: SystemTime,
+    ) -> Result<A, ValidationError<A::ParseError>> {
+        use ValidationStage::*;
 
-        if config.check_expiration && access_token.is_expired() {
-            return Err(ExpiredAccessToken);
+        let metadata = || TokenMetadata::with_key_id(key_id);
+        let unknown_bits = access_token.permissions().unknown_bits();
+        if !unknown_bits.is_empty() {
+            match config.unknown_permissions {
+                UnknownPermissionPolicy::Reject => {
+                    let metadata = TokenMetadata {
+                        unknown_permissions: unknown_bits.to_vec(),
+                        ..metadata()
+                    };
+                    return Err(ValidationError::new(UnknownPermission, Parse, metadata));
+                }
+                UnknownPermissionPolicy::Ignore => {}
+                UnknownPermissionPolicy::Report => warn!(
+                    unknown_permissions = ?unknown_bits,
+                    key_id,
+                    "access token contains unknown permissions"
+                ),
+            }
+        }
+
+        let claims_error =
+            |kind| ValidationError::new(kind, Claims, metadata().with_claims(&access_token));
+        // 4. check validity period
+        if config.check_expiration {
+            check_validity_period(&access_token, now, config.leeway).map_err(claims_error)?;
+        }
+        // 5. check that the token is issued by expected issuer for this server
+        check_registered_claims(&access_token, config).map_err(claims_error)?;
+        // 6. check that the token has not been revoked
+        if let Some(store) = &self.revocation_store {
+            let query = RevocationQuery {
+                token_id: access_token.token_id(),
+                subject: access_token.subject(),
+                issued_at: access_token.issued_at(),
+            };
+            if store.is_revoked(&query) {
+                let metadata = metadata().with_claims(&access_token);
+                return Err(ValidationError::new(
+                    RevokedAccessToken,
+                    Revocation,
+                    metadata,
+                ));
+            }
         }
 
         Ok(access_token)
     }
 }
 
-#[cfg(test)]
-macro_rules! assert_auth_error {
-    ($exp:expr, $err:path) => {
-        assert!(
-            matches!($exp, Err($err)),
-            concat!("Expect Err(", stringify!($err), ") but found {:?}"),
-            $exp
-        );
-    };
+fn check_validity_period<A: AccessToken>(
+    access_token: &A,
+    now: SystemTime,
+    leeway: Duration,
+) -> Result<(), Error> {
+    let is_future = |time: SystemTime| now + leeway < time;
+    // Token issued in the future is not valid yet as well
+    if access_token.issued_at().is_some_and(is_future)
+        || access_token.not_before().is_some_and(is_future)
+    {
+        return Err(NotYetValidAccessToken);
+    }
+    let is_expired = |expires_at: SystemTime| expires_at + leeway <= now;
+    if access_token.is_expired() || access_token.expires_at().is_some_and(is_expired) {
+        return Err(ExpiredAccessToken);
+    }
+    Ok(())
+}
+
+fn check_registered_claims<A: AccessToken>(
+    access_token: &A,
+    config: &ValidationConfig,
+) -> Result<(), Error> {
+    if let Some(issuer) = &config.issuer {
+        if access_token.issuer() != Some(issuer.as_str()) {
+            return Err(InvalidIssuer);
+        }
+    }
+    if !config.audience.is_empty()
+        && !access_token
+            .audience()
+            .iter()
+            .any(|audience| config.audience.contains(audience))
+    {
+        return Err(InvalidAudience);
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::crypto::tests::{get_test_private_key, get_test_public_key};
-    use crate::crypto::PrivateKey;
-    use crate::rbac::test_helpers::TestPermission::{Permission1, Permission2};
-    use crate::token::test_utils::TestAccessToken;
-
-    use super::*;
+    use std::time::UNIX_EPOCH;
 
-    type ValidateResult = Result<TestAccessToken, Error>;
+    use crate::crypto::tests::{get_test_ecdsa_key, get_test_public_key, get_test_rsa_key};
+    use crate::crypto::{PrivateKey, PublicKey, Signer, VerifyingKey};
+    use crate::rbac::test_helpers::TestPermission::{Permission0, Permission1, Permission2};
+    use crate::rbac::Predicate;
+    use crate::token::test_utils::{
+        issue, make_validator, private_key, TestAccessToken, ValidateResult,
+    };
+    use crate::token::InMemoryRevocationStore;
+    use crate::token::{IssuanceConfig, TokenIssuer};
 
-    fn create_access_token_with_key(token: TestAccessToken, private_key: &PrivateKey) -> String {
-        SignedMessage::create(token.to_bytes(), &private_key).encode()
-    }
+    use super::*;
 
-    fn create_access_token(token: TestAccessToken) -> String {
-        let private_key = PrivateKey::from_base64(&get_test_private_key()).unwrap();
-        create_access_token_with_key(token, &private_key)
+    fn create_access_token_with_key(token: TestAccessToken, private_key: PrivateKey) -> String {
+        TokenIssuer::new(private_key).issue(token)
     }
 
-    fn make_validator() -> TokenValidator {
-        TokenValidator::new(PublicKey::from_base64(&get_test_public_key()).unwrap())
+    fn create_access_token_at_fixed_time(token: TestAccessToken) -> String {
+        let config = IssuanceConfig {
+            clock: fixed_clock,
+            ..Default::default()
+        };
+        TokenIssuer::with_config(private_key(), config).issue(token)
     }
 
     #[test]
@@ -116,24 +670,430 @@ mod tests {
         let validator = make_validator();
 
         let token = TestAccessToken::new(vec![Permission1, Permission2].into(), false);
-        let access_token = create_access_token_with_key(token, &private_key_other);
+        let access_token = create_access_token_with_key(token, private_key_other);
 
         let x: ValidateResult = validator.validate(access_token);
         assert_auth_error!(x, SignatureVerificationFail);
     }
 
+    #[test]
+    fn test_key_rotation() {
+        let private_key_other =
+            PrivateKey::from_base64("B1H3hDtRa0K0XxPC2tjD8uj2Tx3i9RlsQ7jSpl4OOIY").unwrap();
+        let public_key_other =
+            PublicKey::from_base64("uneKfdOZUuupqMK7q1KwPFluM9zxpdIlyNntF4V1Dgs").unwrap();
+
+        let mut key_ring = KeyRing::new();
+        key_ring.insert(
+            "old",
+            PublicKey::from_base64(&get_test_public_key()).unwrap(),
+        );
+        key_ring.insert("new", public_key_other);
+        let mut validator = TokenValidator::with_key_ring(key_ring);
+
+        let mut issuer = TokenIssuer::with_key_id("old", private_key());
+        let old_token = issuer.issue(TestAccessToken::new(vec![Permission1].into(), false));
+        issuer.rotate("new", private_key_other);
+        let new_token = issuer.issue(TestAccessToken::new(vec![Permission1].into(), false));
+
+        let x: ValidateResult = validator.validate(&old_token);
+        assert!(x.is_ok());
+        let x: ValidateResult = validator.validate(&new_token);
+        assert!(x.is_ok());
+
+        validator.key_ring_mut().retire("old", Duration::ZERO);
+        let x: ValidateResult = validator.validate(&old_token);
+        assert_auth_error!(x, UnknownSigningKey);
+        let x: ValidateResult = validator.validate(&new_token);
+        assert!(x.is_ok());
+
+        // token without key id requires default key
+        let x: ValidateResult =
+            validator.validate(issue(TestAccessToken::new(vec![Permission1].into(), false)));
+        assert_auth_error!(x, UnknownSigningKey);
+    }
+
     #[test]
     fn test_access_token() {
         let validator = make_validator();
 
-        let token = create_access_token(TestAccessToken::new(vec![Permission1].into(), true));
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), true));
         let x: ValidateResult = validator.validate(token);
         assert_auth_error!(x, ExpiredAccessToken);
     }
 
+    fn fixed_clock() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+    }
+
+    fn validate_at_fixed_time(token: TestAccessToken, leeway: Duration) -> ValidateResult {
+        let config = ValidationConfig {
+            leeway,
+            clock: fixed_clock,
+            ..Default::default()
+        };
+        make_validator().validate_config(create_access_token_at_fixed_time(token), &config)
+    }
+
+    #[test]
+    fn test_expires_at() {
+        let now = fixed_clock();
+        let second = Duration::from_secs(1);
+        let token = || TestAccessToken::new(vec![Permission1].into(), false);
+
+        let x = validate_at_fixed_time(token().with_expires_at(now + second), Duration::ZERO);
+        assert!(x.is_ok());
+        let x = validate_at_fixed_time(token().with_expires_at(now), Duration::ZERO);
+        assert_auth_error!(x, ExpiredAccessToken);
+        let x = validate_at_fixed_time(token().with_expires_at(now), 2 * second);
+        assert!(x.is_ok());
+        let x = validate_at_fixed_time(token().with_expires_at(now - 2 * second), 2 * second);
+        assert_auth_error!(x, ExpiredAccessToken);
+        // `is_expired` of the implementor is checked as well
+        let expired = TestAccessToken::new(vec![Permission1].into(), true);
+        let x = validate_at_fixed_time(expired.with_expires_at(now + second), Duration::ZERO);
+        assert_auth_error!(x, ExpiredAccessToken);
+    }
+
+    #[test]
+    fn test_not_before() {
+        let now = fixed_clock();
+        let second = Duration::from_secs(1);
+        let token = || TestAccessToken::new(vec![Permission1].into(), false);
+
+        let x = validate_at_fixed_time(token().with_not_before(now), Duration::ZERO);
+        assert!(x.is_ok());
+        let x = validate_at_fixed_time(token().with_not_before(now + second), Duration::ZERO);
+        assert_auth_error!(x, NotYetValidAccessToken);
+        let x = validate_at_fixed_time(token().with_not_before(now + second), second);
+        assert!(x.is_ok());
+    }
+
+    #[test]
+    fn test_skip_expiration_check() {
+        let token = TestAccessToken::new(vec![Permission1].into(), true)
+            .with_expires_at(fixed_clock() - Duration::from_secs(60));
+        let config = ValidationConfig {
+            check_expiration: false,
+            clock: fixed_clock,
+            ..Default::default()
+        };
+        let x: ValidateResult =
+            make_validator().validate_config(create_access_token_at_fixed_time(token), &config);
+        assert!(x.is_ok());
+    }
+
+    #[test]
+    fn test_validation_error_details() {
+        let validator = make_validator();
+        let x: ValidateResult = validator.validate("123");
+        let err = x.unwrap_err();
+        assert_eq!(err.stage(), ValidationStage::Decode);
+        assert!(matches!(Error::from(err), InvalidSignedMessage));
+
+        let token = SignedMessage::create_with_key_id(vec![0xff], "k1".into(), &private_key());
+        let mut validator = make_validator();
+        validator.key_ring_mut().insert(
+            "k1",
+            PublicKey::from_base64(&get_test_public_key()).unwrap(),
+        );
+        let x: ValidateResult = validator.validate(token.encode());
+        let err = x.unwrap_err();
+        assert!(matches!(err.kind(), InvalidAccessToken));
+        assert_eq!(err.stage(), ValidationStage::Parse);
+        assert_eq!(err.parse_error(), Some(&()));
+        assert_eq!(err.metadata().key_id.as_deref(), Some("k1"));
+
+        let expires_at = fixed_clock() - Duration::from_secs(1);
+        let token =
+            TestAccessToken::new(vec![Permission1].into(), false).with_expires_at(expires_at);
+        let err = validate_at_fixed_time(token, Duration::ZERO).unwrap_err();
+        assert_eq!(err.stage(), ValidationStage::Claims);
+        assert_eq!(err.metadata().expires_at, Some(expires_at));
+        assert_eq!(err.to_string(), "expired access token at claims stage");
+    }
+
+    #[test]
+    fn test_unknown_permissions() {
+        // Permission0 and unknown permission at index 17
+        let mut builder = crate::protos::token::TestAccessToken::new();
+        builder.permissions = vec![0b1000_0000, 0, 0b0100_0000];
+        let message = protobuf::Message::write_to_bytes(&builder).unwrap();
+        let token = SignedMessage::create(message, &private_key()).encode();
+        let validate = |unknown_permissions| -> ValidateResult {
+            let config = ValidationConfig {
+                unknown_permissions,
+                ..Default::default()
+            };
+            make_validator().validate_config(&token, &config)
+        };
+
+        let err = validate(UnknownPermissionPolicy::Reject).unwrap_err();
+        assert!(matches!(err.kind(), UnknownPermission));
+        assert_eq!(err.metadata().unknown_permissions, vec![17]);
+        for policy in [
+            UnknownPermissionPolicy::Ignore,
+            UnknownPermissionPolicy::Report,
+        ] {
+            let x = validate(policy).unwrap();
+            assert_eq!(x.permissions().unknown_bits(), &[17]);
+            let decision = make_validator().authorize(&x, Predicate::contains(Permission0));
+            assert!(decision.is_granted());
+        }
+    }
+
+    #[test]
+    fn test_registered_claims() {
+        let validate = |token: TestAccessToken| -> ValidateResult {
+            let config = ValidationConfig {
+                issuer: Some(String::from("https://id.example.com")),
+                audience: vec![String::from("api"), String::from("admin")],
+                ..Default::default()
+            };
+            make_validator().validate_config(issue(token), &config)
+        };
+        let token = || {
+            TestAccessToken::new(vec![Permission1].into(), false)
+                .with_subject("user-1")
+                .with_token_id("token-1")
+        };
+
+        let x = validate(
+            token()
+                .with_issuer("https://id.example.com")
+                .with_audience(&["web", "api"]),
+        );
+        let x = x.unwrap();
+        assert_eq!(x.subject(), Some("user-1"));
+        assert_eq!(x.token_id(), Some("token-1"));
+
+        let x = validate(
+            token()
+                .with_issuer("https://evil.example.com")
+                .with_audience(&["api"]),
+        );
+        assert_auth_error!(x, InvalidIssuer);
+        assert_eq!(
+            x.unwrap_err().metadata().issuer.as_deref(),
+            Some("https://evil.example.com")
+        );
+        let x = validate(token().with_audience(&["api"]));
+        assert_auth_error!(x, InvalidIssuer);
+        let x = validate(
+            token()
+                .with_issuer("https://id.example.com")
+                .with_audience(&["web"]),
+        );
+        assert_auth_error!(x, InvalidAudience);
+        let x = validate(token().with_issuer("https://id.example.com"));
+        assert_auth_error!(x, InvalidAudience);
+
+        // claims are not required by default
+        let x: ValidateResult = make_validator().validate(issue(token()));
+        assert!(x.is_ok());
+    }
+
+    #[test]
+    fn test_revoked_access_token() {
+        let store = Arc::new(InMemoryRevocationStore::new());
+        let mut validator = make_validator();
+        validator.set_revocation_store(Some(store.clone()));
+        let token = |token_id| {
+            issue(
+                TestAccessToken::new(vec![Permission1].into(), false)
+                    .with_subject("user-1")
+                    .with_token_id(token_id),
+            )
+        };
+
+        let x: ValidateResult = validator.validate(token("token-1"));
+        assert!(x.is_ok());
+
+        store.update(|list| list.revoke_token_id("token-1"));
+        let x: ValidateResult = validator.validate(token("token-1"));
+        assert_auth_error!(x, RevokedAccessToken);
+        assert_eq!(x.unwrap_err().stage(), ValidationStage::Revocation);
+        let x: ValidateResult = validator.validate(token("token-2"));
+        assert!(x.is_ok());
+
+        store.update(|list| list.revoke_subject("user-1"));
+        let x: ValidateResult = validator.validate(token("token-2"));
+        assert_auth_error!(x, RevokedAccessToken);
+    }
+
+    #[test]
+    fn test_signature_algorithms() {
+        let config = ValidationConfig {
+            allowed_algorithms: Some(vec![
+                Algorithm::EcdsaP256Sha256,
+                Algorithm::EcdsaP384Sha384,
+                Algorithm::RsaPssSha256,
+            ]),
+            ..Default::default()
+        };
+        let ec256 = get_test_ecdsa_key(Algorithm::EcdsaP256Sha256);
+        let ec384 = get_test_ecdsa_key(Algorithm::EcdsaP384Sha384);
+        let rsa = get_test_rsa_key(Algorithm::RsaPssSha256);
+        let mut key_ring = KeyRing::new();
+        key_ring.insert("ec256", ec256.public_key());
+        key_ring.insert("ec384", ec384.public_key());
+        key_ring.insert("rsa", rsa.public_key());
+        let validator = TokenValidator::with_key_ring(key_ring);
+
+        let token = || TestAccessToken::new(vec![Permission1].into(), false);
+        for (key_id, signer) in [
+            ("ec256", &ec256 as &dyn Signer),
+            ("ec384", &ec384),
+            ("rsa", &rsa),
+        ] {
+            let message = token().to_bytes();
+            let signed = SignedMessage::create_with_key_id(message, key_id.into(), signer);
+            let x: ValidateResult = validator.validate_config(signed.encode(), &config);
+            assert!(x.is_ok(), "{key_id} should validate");
+        }
+
+        // key id pointing to a key of another algorithm
+        let message = token().to_bytes();
+        let signed = SignedMessage::create_with_key_id(message, "ec384".into(), &ec256);
+        let x: ValidateResult = validator.validate_config(signed.encode(), &config);
+        assert_auth_error!(x, SignatureVerificationFail);
+
+        // Ed25519 is not allowed by this config
+        let x: ValidateResult = make_validator().validate_config(issue(token()), &config);
+        assert_auth_error!(x, DisallowedAlgorithm);
+    }
+
+    #[test]
+    fn test_reject_algorithm_downgrade() {
+        let rsa = get_test_rsa_key(Algorithm::RsaPssSha256);
+        let pkcs1 = get_test_rsa_key(Algorithm::RsaPkcs1Sha256);
+        let verifying_key =
+            VerifyingKey::new(Algorithm::RsaPkcs1Sha256, rsa.public_key().as_bytes());
+        let validator = TokenValidator::new(verifying_key);
+        let config = ValidationConfig {
+            allowed_algorithms: Some(vec![Algorithm::RsaPssSha256]),
+            ..Default::default()
+        };
+        let token =
+            TokenIssuer::new(pkcs1).issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let x: ValidateResult = validator.validate_config(&token, &config);
+        assert_auth_error!(x, DisallowedAlgorithm);
+        assert_eq!(x.unwrap_err().stage(), ValidationStage::Signature);
+    }
+
+    #[test]
+    fn test_hmac_token() {
+        let hmac_key = || HmacKey::new(Algorithm::HmacSha256, &[7; 32]).unwrap();
+        let config = ValidationConfig {
+            allowed_algorithms: Some(vec![Algorithm::Ed25519, Algorithm::HmacSha256]),
+            ..Default::default()
+        };
+        let token = TokenIssuer::new(hmac_key())
+            .issue(TestAccessToken::new(vec![Permission1].into(), false));
+
+        // algorithm of the shared secret is allowed by default
+        let x: ValidateResult = HmacTokenValidator::with_hmac_key(hmac_key()).validate(&token);
+        assert!(x.is_ok());
+        let x: ValidateResult =
+            HmacTokenValidator::with_hmac_key(hmac_key()).validate_config(&token, &config);
+        assert!(x.is_ok());
+        let only_ed25519 = ValidationConfig {
+            allowed_algorithms: Some(vec![Algorithm::Ed25519]),
+            ..Default::default()
+        };
+        let x: ValidateResult =
+            HmacTokenValidator::with_hmac_key(hmac_key()).validate_config(&token, &only_ed25519);
+        assert_auth_error!(x, DisallowedAlgorithm);
+        let sha512_key = HmacKey::new(Algorithm::HmacSha512, &[7; 64]).unwrap();
+        let x: ValidateResult = HmacTokenValidator::with_hmac_key(sha512_key).validate(&token);
+        assert_auth_error!(x, DisallowedAlgorithm);
+        // public-key validator has no secret to verify with
+        let x: ValidateResult = make_validator().validate_config(&token, &config);
+        assert_auth_error!(x, UnknownSigningKey);
+        let other_key = HmacKey::new(Algorithm::HmacSha256, &[8; 32]).unwrap();
+        let x: ValidateResult =
+            HmacTokenValidator::with_hmac_key(other_key).validate_config(&token, &config);
+        assert_auth_error!(x, SignatureVerificationFail);
+
+        // HMAC validator never trusts public-key tokens
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let x: ValidateResult =
+            HmacTokenValidator::with_hmac_key(hmac_key()).validate_config(&token, &config);
+        assert_auth_error!(x, UnknownSigningKey);
+    }
+
+    #[test]
+    fn test_encrypted_token() {
+        let encryption_key = || EncryptionKey::from_bytes(&[7; 32]).unwrap();
+        let mut issuer = TokenIssuer::new(private_key());
+        let token = || TestAccessToken::new(vec![Permission1].into(), false);
+        assert!(issuer.issue_encrypted(token()).is_none());
+        issuer.set_encryption_key("e1", encryption_key());
+        let encrypted = issuer.issue_encrypted(token()).unwrap();
+
+        let mut validator = make_validator();
+        let x: ValidateResult = validator.validate_encrypted(&encrypted);
+        assert_auth_error!(x, UnknownEncryptionKey);
+        assert_eq!(x.unwrap_err().stage(), ValidationStage::Decryption);
+
+        validator.insert_encryption_key("e1", EncryptionKey::from_bytes(&[8; 32]).unwrap());
+        let x: ValidateResult = validator.validate_encrypted(&encrypted);
+        assert_auth_error!(x, DecryptionFail);
+
+        validator.insert_encryption_key("e1", encryption_key());
+        let x: ValidateResult = validator.validate_encrypted(&encrypted);
+        let decision = validator.authorize(&x.unwrap(), Predicate::contains(Permission1));
+        assert!(decision.is_granted());
+        // payload cannot be read as a signed message
+        let x: ValidateResult = validator.validate(&encrypted);
+        assert!(x.is_err());
+
+        // signature is still verified after decryption
+        let other_key =
+            PrivateKey::from_base64("B1H3hDtRa0K0XxPC2tjD8uj2Tx3i9RlsQ7jSpl4OOIY").unwrap();
+        let mut issuer = TokenIssuer::new(other_key);
+        issuer.set_encryption_key("e1", encryption_key());
+        let x: ValidateResult =
+            validator.validate_encrypted(issuer.issue_encrypted(token()).unwrap());
+        assert_auth_error!(x, SignatureVerificationFail);
+    }
+
+    #[test]
+    fn test_legacy_format() {
+        let config = IssuanceConfig {
+            legacy_format: true,
+            ..Default::default()
+        };
+        let token = TokenIssuer::with_config(private_key(), config)
+            .issue(TestAccessToken::new(vec![Permission1].into(), false));
+        assert_eq!(token.split('.').count(), 2);
+
+        let x: ValidateResult = make_validator().validate(&token);
+        assert_auth_error!(x, InvalidSignedMessage);
+        let config = ValidationConfig {
+            accept_legacy_format: true,
+            ..Default::default()
+        };
+        let x: ValidateResult = make_validator().validate_config(&token, &config);
+        assert!(x.is_ok());
+        // versioned tokens are accepted as well
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), false));
+        assert!(token.starts_with("v1."));
+        let x: ValidateResult = make_validator().validate_config(&token, &config);
+        assert!(x.is_ok());
+        let x: ValidateResult = make_validator().validate_config(format!("{token}.extra"), &config);
+        assert_auth_error!(x, InvalidSignedMessage);
+    }
+
     #[test]
     fn test_default_validation_config() {
         let config = <ValidationConfig as Default>::default();
         assert!(config.check_expiration, "default must check expiration");
+        assert_eq!(config.leeway, Duration::ZERO);
+        assert_eq!(config.unknown_permissions, UnknownPermissionPolicy::Report);
+        assert!(config.issuer.is_none());
+        assert!(config.audience.is_empty());
+        assert!(config.allowed_algorithms.is_none());
+        assert!(!config.accept_legacy_format);
     }
 }
@@ -0,0 +1,374 @@
+//! Tonic gRPC integration, available with `tonic` feature
+//!
+//! [`AuthInterceptor`] validates the `authorization: Bearer` metadata and puts the validated
+//! access token into request extensions, where handlers can take it with
+//! `request.extensions().get::<A>()`. Tonic interceptors do not see the called method, so
+//! per-method requirements are checked by [`AuthLayer`] with [`MethodPermissions`], which wraps
+//! the generated server as a tower layer.
+//!
+//! Both work with either validator, `HmacTokenValidator` suits internal service-to-service
+//! calls.
+//!
+//! ```ignore
+//! let mut permissions = MethodPermissions::new();
+//! permissions.insert("/shop.Orders/Create", Predicate::contains(MyPermission::EditOrders));
+//! let layer = AuthLayer::<MyAccessToken>::new(validator, permissions);
+//!
+//! Server::builder()
+//!     .add_service(layer.layer(OrdersServer::new(orders)))
+//!     .serve(addr)
+//!     .await?;
+//! ```
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::{header, HeaderMap};
+use tonic::service::Interceptor;
+use tonic::{Request, Status};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::bearer::bearer_token;
+use crate::rbac::{Permission, Predicate};
+use crate::token::{AccessToken, KeyRing, TokenValidator, ValidationConfig, VerificationKeys};
+use crate::Error;
+
+impl From<Error> for Status {
+    /// `PERMISSION_DENIED` if [`Error::is_forbidden`] and `UNAUTHENTICATED` otherwise
+    fn from(error: Error) -> Self {
+        if error.is_forbidden() {
+            Status::permission_denied(error.to_string())
+        } else {
+            Status::unauthenticated(error.to_string())
+        }
+    }
+}
+
+/// Interceptor which rejects requests without a valid bearer token
+///
+/// Validates with keys `K` of the validator, e.g. `HmacKey` of `HmacTokenValidator` for
+/// service-to-service calls.
+pub struct AuthInterceptor<A, K = KeyRing> {
+    validator: Arc<TokenValidator<K>>,
+    config: Arc<ValidationConfig>,
+    _token: PhantomData<fn() -> A>,
+}
+
+impl<A, K> AuthInterceptor<A, K> {
+    pub fn new(validator: Arc<TokenValidator<K>>) -> Self {
+        Self::with_config(validator, ValidationConfig::default())
+    }
+
+    pub fn with_config(validator: Arc<TokenValidator<K>>, config: ValidationConfig) -> Self {
+        Self {
+            validator,
+            config: Arc::new(config),
+            _token: PhantomData,
+        }
+    }
+}
+
+impl<A: AccessToken, K: VerificationKeys> AuthInterceptor<A, K> {
+    fn authenticate(&self, authorization: Option<&[u8]>) -> Result<A, Error> {
+        let token = authorization
+            .and_then(bearer_token)
+            .ok_or(Error::MissingAccessToken)?;
+        self.validator
+            .validate_config(token, &self.config)
+            .map_err(Error::from)
+    }
+}
+
+impl<A, K> Clone for AuthInterceptor<A, K> {
+    fn clone(&self) -> Self {
+        Self {
+            validator: self.validator.clone(),
+            config: self.config.clone(),
+            _token: PhantomData,
+        }
+    }
+}
+
+impl<A, K> Interceptor for AuthInterceptor<A, K>
+where
+    A: AccessToken + Clone + Send + Sync + 'static,
+    K: VerificationKeys,
+{
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let authorization = request.metadata().get("authorization");
+        let access_token = self.authenticate(authorization.map(|value| value.as_bytes()))?;
+        request.extensions_mut().insert(access_token);
+        Ok(request)
+    }
+}
+
+/// Predicates required by gRPC methods
+///
+/// Methods are identified by their path, `/package.Service/Method`. Requests to methods which
+/// are not listed are denied unless a default predicate is set.
+#[derive(Debug, Clone)]
+pub struct MethodPermissions<P: Permission> {
+    methods: BTreeMap<String, Predicate<P>>,
+    default_predicate: Option<Predicate<P>>,
+}
+
+impl<P: Permission> MethodPermissions<P> {
+    pub fn new() -> Self {
+        Self {
+            methods: BTreeMap::new(),
+            default_predicate: None,
+        }
+    }
+
+    pub fn insert<T: Into<String>>(
+        &mut self,
+        path: T,
+        predicate: Predicate<P>,
+    ) -> Option<Predicate<P>> {
+        self.methods.insert(path.into(), predicate)
+    }
+
+    pub fn remove(&mut self, path: &str) -> Option<Predicate<P>> {
+        self.methods.remove(path)
+    }
+
+    pub fn set_default_predicate(&mut self, predicate: Option<Predicate<P>>) {
+        self.default_predicate = predicate;
+    }
+
+    pub fn find(&self, path: &str) -> Option<&Predicate<P>> {
+        self.methods.get(path).or(self.default_predicate.as_ref())
+    }
+}
+
+impl<P: Permission> Default for MethodPermissions<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Layer which authenticates requests and checks [`MethodPermissions`]
+pub struct AuthLayer<A: AccessToken, K = KeyRing> {
+    interceptor: AuthInterceptor<A, K>,
+    permissions: Arc<MethodPermissions<A::Permission>>,
+}
+
+impl<A: AccessToken, K: VerificationKeys> AuthLayer<A, K> {
+    pub fn new(
+        validator: Arc<TokenValidator<K>>,
+        permissions: MethodPermissions<A::Permission>,
+    ) -> Self {
+        Self::with_config(validator, ValidationConfig::default(), permissions)
+    }
+
+    pub fn with_config(
+        validator: Arc<TokenValidator<K>>,
+        config: ValidationConfig,
+        permissions: MethodPermissions<A::Permission>,
+    ) -> Self {
+        Self {
+            interceptor: AuthInterceptor::with_config(validator, config),
+            permissions: Arc::new(permissions),
+        }
+    }
+
+    fn authorize(&self, path: &str, headers: &HeaderMap) -> Result<A, Error> {
+        let authorization = headers.get(header::AUTHORIZATION);
+        let access_token = self
+            .interceptor
+            .authenticate(authorization.map(|value| value.as_bytes()))?;
+        // Unlisted methods require the predicate which is never satisfied
+        let deny = Predicate::any([]);
+        let predicate = self.permissions.find(path).unwrap_or(&deny);
+        let AuthInterceptor {
+            validator, config, ..
+        } = &self.interceptor;
+        if !validator
+            .authorize_config(&access_token, predicate, config)
+            .is_granted()
+        {
+            return Err(Error::Unauthorized);
+        }
+        Ok(access_token)
+    }
+}
+
+impl<A: AccessToken, K> Clone for AuthLayer<A, K> {
+    fn clone(&self) -> Self {
+        Self {
+            interceptor: self.interceptor.clone(),
+            permissions: self.permissions.clone(),
+        }
+    }
+}
+
+impl<S, A: AccessToken, K> Layer<S> for AuthLayer<A, K> {
+    type Service = AuthService<S, A, K>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// Service created by [`AuthLayer`]
+pub struct AuthService<S, A: AccessToken, K = KeyRing> {
+    inner: S,
+    layer: AuthLayer<A, K>,
+}
+
+impl<S: Clone, A: AccessToken, K> Clone for AuthService<S, A, K> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<S, A, K, B, R> Service<http::Request<B>> for AuthService<S, A, K>
+where
+    S: Service<http::Request<B>, Response = http::Response<R>>,
+    S::Future: Send + 'static,
+    A: AccessToken + Clone + Send + Sync + 'static,
+    K: VerificationKeys,
+    R: Default + Send + 'static,
+{
+    type Response = http::Response<R>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        match self
+            .layer
+            .authorize(request.uri().path(), request.headers())
+        {
+            Ok(access_token) => {
+                request.extensions_mut().insert(access_token);
+                Box::pin(self.inner.call(request))
+            }
+            Err(error) => {
+                let response = Status::from(error).into_http();
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use tonic::metadata::MetadataValue;
+    use tonic::Code;
+    use tower::{service_fn, ServiceExt};
+
+    use crate::crypto::{Algorithm, HmacKey};
+    use crate::rbac::test_helpers::TestPermission::{self, Permission1, Permission2};
+    use crate::token::test_utils::{issue, make_validator, TestAccessToken};
+    use crate::token::{HmacTokenValidator, TokenIssuer};
+
+    use super::*;
+
+    fn code<R>(response: &http::Response<R>) -> Code {
+        Status::from_header_map(response.headers()).map_or(Code::Ok, |status| status.code())
+    }
+
+    async fn call(path: &str, authorization: Option<String>) -> http::Response<String> {
+        call_with(Arc::new(make_validator()), path, authorization).await
+    }
+
+    async fn call_with<K: VerificationKeys>(
+        validator: Arc<TokenValidator<K>>,
+        path: &str,
+        authorization: Option<String>,
+    ) -> http::Response<String> {
+        let mut permissions = MethodPermissions::<TestPermission>::new();
+        permissions.insert("/test.Service/Read", Predicate::contains(Permission1));
+        permissions.insert("/test.Service/Write", Predicate::contains(Permission2));
+        let layer = AuthLayer::<TestAccessToken, K>::new(validator, permissions);
+        let service = layer.layer(service_fn(|request: http::Request<()>| async move {
+            let access_token = request.extensions().get::<TestAccessToken>().unwrap();
+            let count = access_token.permissions().iter().count();
+            Ok::<_, Infallible>(http::Response::new(count.to_string()))
+        }));
+
+        let mut request = http::Request::builder().uri(path);
+        if let Some(authorization) = authorization {
+            request = request.header(header::AUTHORIZATION, authorization);
+        }
+        service.oneshot(request.body(()).unwrap()).await.unwrap()
+    }
+
+    #[test]
+    fn interceptor_should_insert_access_token() {
+        let validator = Arc::new(make_validator());
+        let mut interceptor = AuthInterceptor::<TestAccessToken>::new(validator.clone());
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), false));
+
+        let mut request = Request::new(());
+        let value = MetadataValue::try_from(format!("Bearer {token}")).unwrap();
+        request.metadata_mut().insert("authorization", value);
+        let request = interceptor.call(request).unwrap();
+        let access_token = request.extensions().get::<TestAccessToken>().unwrap();
+        let decision = validator.authorize(access_token, Predicate::contains(Permission1));
+        assert!(decision.is_granted());
+
+        let status = interceptor.call(Request::new(())).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+    }
+
+    #[tokio::test]
+    async fn layer_should_check_method_permissions() {
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let response = call("/test.Service/Read", Some(format!("Bearer {token}"))).await;
+        assert_eq!(code(&response), Code::Ok);
+        assert_eq!(response.body(), "1");
+
+        for path in ["/test.Service/Write", "/test.Service/Unlisted"] {
+            let response = call(path, Some(format!("Bearer {token}"))).await;
+            assert_eq!(code(&response), Code::PermissionDenied);
+        }
+    }
+
+    #[tokio::test]
+    async fn layer_should_reject_invalid_tokens() {
+        let expired = issue(TestAccessToken::new(vec![Permission1].into(), true));
+        for authorization in [
+            None,
+            Some("Bearer 123".to_owned()),
+            Some(format!("Bearer {expired}")),
+        ] {
+            let response = call("/test.Service/Read", authorization).await;
+            assert_eq!(code(&response), Code::Unauthenticated);
+        }
+    }
+
+    #[tokio::test]
+    async fn layer_should_validate_with_shared_secret() {
+        let hmac_key = || HmacKey::new(Algorithm::HmacSha256, &[7; 32]).unwrap();
+        let validator = Arc::new(HmacTokenValidator::with_hmac_key(hmac_key()));
+        let token = TokenIssuer::new(hmac_key())
+            .issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let authorization = Some(format!("Bearer {token}"));
+        let response = call_with(validator.clone(), "/test.Service/Read", authorization).await;
+        assert_eq!(code(&response), Code::Ok);
+
+        // public-key tokens are not trusted by the shared secret
+        let token = issue(TestAccessToken::new(vec![Permission1].into(), false));
+        let authorization = Some(format!("Bearer {token}"));
+        let response = call_with(validator, "/test.Service/Read", authorization).await;
+        assert_eq!(code(&response), Code::Unauthenticated);
+    }
+}
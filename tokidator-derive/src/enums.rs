@@ -0,0 +1,113 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, Ident, Result, Variant};
+
+/// Fieldless enum which can be converted to and from primitive
+pub(crate) struct UnitEnum<'a> {
+    pub ident: &'a Ident,
+    pub variants: Vec<&'a Variant>,
+}
+
+impl<'a> UnitEnum<'a> {
+    pub fn from_input(input: &'a DeriveInput, derive: &str) -> Result<Self> {
+        let data = match &input.data {
+            Data::Enum(data) => data,
+            _ => {
+                let message = format!("#[derive({})] is only supported on enums", derive);
+                return Err(Error::new_spanned(&input.ident, message));
+            }
+        };
+        if !input.generics.params.is_empty() {
+            let message = format!("#[derive({})] does not support generic enums", derive);
+            return Err(Error::new_spanned(&input.generics, message));
+        }
+        for variant in &data.variants {
+            if !matches!(variant.fields, Fields::Unit) {
+                let message = format!("#[derive({})] variants must not have fields", derive);
+                return Err(Error::new_spanned(variant, message));
+            }
+        }
+        Ok(Self {
+            ident: &input.ident,
+            variants: data.variants.iter().collect(),
+        })
+    }
+
+    /// Implement comparison and primitive conversion traits based on discriminants
+    ///
+    /// Also assert at compile time that every discriminant is non-negative, which is required
+    /// for using discriminants as bit indexes. Rustc already rejects duplicate discriminants.
+    pub fn expand_common_traits(&self) -> TokenStream {
+        let ident = self.ident;
+        let variants: Vec<_> = self.variants.iter().map(|v| &v.ident).collect();
+        let num_traits = quote!(::tokidator::__private::num_traits);
+        quote! {
+            impl ::core::clone::Clone for #ident {
+                #[inline]
+                fn clone(&self) -> Self {
+                    *self
+                }
+            }
+
+            impl ::core::marker::Copy for #ident {}
+
+            impl ::core::cmp::PartialEq for #ident {
+                #[inline]
+                fn eq(&self, other: &Self) -> bool {
+                    (*self as i64) == (*other as i64)
+                }
+            }
+
+            impl ::core::cmp::Eq for #ident {}
+
+            impl ::core::cmp::PartialOrd for #ident {
+                #[inline]
+                fn partial_cmp(&self, other: &Self) -> ::core::option::Option<::core::cmp::Ordering> {
+                    ::core::option::Option::Some(::core::cmp::Ord::cmp(self, other))
+                }
+            }
+
+            impl ::core::cmp::Ord for #ident {
+                #[inline]
+                fn cmp(&self, other: &Self) -> ::core::cmp::Ordering {
+                    ::core::cmp::Ord::cmp(&(*self as i64), &(*other as i64))
+                }
+            }
+
+            impl #num_traits::FromPrimitive for #ident {
+                #[allow(non_upper_case_globals)]
+                fn from_i64(n: i64) -> ::core::option::Option<Self> {
+                    #(const #variants: i64 = #ident::#variants as i64;)*
+                    match n {
+                        #(#variants => ::core::option::Option::Some(Self::#variants),)*
+                        _ => ::core::option::Option::None,
+                    }
+                }
+
+                fn from_u64(n: u64) -> ::core::option::Option<Self> {
+                    i64::try_from(n).ok().and_then(Self::from_i64)
+                }
+            }
+
+            impl #num_traits::ToPrimitive for #ident {
+                #[inline]
+                fn to_i64(&self) -> ::core::option::Option<i64> {
+                    ::core::option::Option::Some(*self as i64)
+                }
+
+                #[inline]
+                fn to_u64(&self) -> ::core::option::Option<u64> {
+                    u64::try_from(*self as i64).ok()
+                }
+            }
+
+            const _: () = {
+                #(
+                    if (#ident::#variants as i64) < 0 {
+                        panic!(concat!("discriminants of `", stringify!(#ident), "` must not be negative"));
+                    }
+                )*
+            };
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Derive macros for `tokidator::rbac::Permission` and `tokidator::rbac::Role`
+//!
+//! Both macros accept enums without payloads. They implement `Clone`, `Copy`, `PartialEq`, `Eq`,
+//! `PartialOrd`, `Ord`, `FromPrimitive` and `ToPrimitive` from enum discriminants, so ordering
+//! always agrees with the primitive value used in encoded permission sets.
+#![deny(rust_2018_idioms)]
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+mod enums;
+mod permission;
+mod role;
+
+#[proc_macro_derive(Permission)]
+pub fn derive_permission(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    permission::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derive `Role`, permission type, role permissions and parent roles are declared with
+/// `role` attribute
+///
+/// ```ignore
+/// #[derive(Debug, Role)]
+/// #[role(permission = MyPermission)]
+/// enum MyRole {
+///     #[role(permissions = [Read])]
+///     Viewer,
+///     #[role(permissions = [Write], parents = [Viewer])]
+///     Editor,
+/// }
+/// ```
+#[proc_macro_derive(Role, attributes(role))]
+pub fn derive_role(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    role::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
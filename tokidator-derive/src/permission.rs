@@ -0,0 +1,16 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, Result};
+
+use crate::enums::UnitEnum;
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let unit_enum = UnitEnum::from_input(&input, "Permission")?;
+    let ident = unit_enum.ident;
+    let common_traits = unit_enum.expand_common_traits();
+    Ok(quote! {
+        #common_traits
+
+        impl ::tokidator::rbac::Permission for #ident {}
+    })
+}
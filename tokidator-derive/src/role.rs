@@ -0,0 +1,109 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{bracketed, Attribute, DeriveInput, Error, Path, Result, Token, Type};
+
+use crate::enums::UnitEnum;
+
+pub(crate) fn expand(input: DeriveInput) -> Result<TokenStream> {
+    let unit_enum = UnitEnum::from_input(&input, "Role")?;
+    let ident = unit_enum.ident;
+    let permission = parse_permission_type(&input)?;
+
+    let mut permission_arms = Vec::with_capacity(unit_enum.variants.len());
+    let mut parent_arms = Vec::with_capacity(unit_enum.variants.len());
+    for variant in &unit_enum.variants {
+        let variant_ident = &variant.ident;
+        let VariantAttributes {
+            permissions,
+            parents,
+        } = parse_variant_attributes(&variant.attrs)?;
+        let permissions = permissions.into_iter().map(|path| match path.get_ident() {
+            // Bare variant name refers to variant of the permission type
+            Some(name) => quote!(<#permission>::#name),
+            None => quote!(#path),
+        });
+        let parents = parents.into_iter().map(|path| match path.get_ident() {
+            Some(name) => quote!(Self::#name),
+            None => quote!(#path),
+        });
+        permission_arms.push(quote! {
+            Self::#variant_ident => &[#(#permissions),*],
+        });
+        parent_arms.push(quote! {
+            Self::#variant_ident => &[#(#parents),*],
+        });
+    }
+
+    let common_traits = unit_enum.expand_common_traits();
+    Ok(quote! {
+        #common_traits
+
+        impl ::tokidator::rbac::Role for #ident {
+            type Permission = #permission;
+
+            fn permissions(&self) -> &[Self::Permission] {
+                match self {
+                    #(#permission_arms)*
+                }
+            }
+
+            fn parents(&self) -> &[Self] {
+                match self {
+                    #(#parent_arms)*
+                }
+            }
+        }
+    })
+}
+
+/// Parse `#[role(permission = Type)]` on the enum
+fn parse_permission_type(input: &DeriveInput) -> Result<Type> {
+    let mut permission = None;
+    for attr in role_attributes(&input.attrs) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("permission") {
+                permission = Some(meta.value()?.parse::<Type>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `permission = Type`"))
+            }
+        })?;
+    }
+    permission.ok_or_else(|| {
+        let message = "missing `#[role(permission = Type)]` attribute";
+        Error::new_spanned(&input.ident, message)
+    })
+}
+
+#[derive(Default)]
+struct VariantAttributes {
+    permissions: Vec<Path>,
+    parents: Vec<Path>,
+}
+
+/// Parse `#[role(permissions = [A, B], parents = [C])]` on a variant
+fn parse_variant_attributes(attrs: &[Attribute]) -> Result<VariantAttributes> {
+    let mut attributes = VariantAttributes::default();
+    for attr in role_attributes(attrs) {
+        attr.parse_nested_meta(|meta| {
+            let list = if meta.path.is_ident("permissions") {
+                &mut attributes.permissions
+            } else if meta.path.is_ident("parents") {
+                &mut attributes.parents
+            } else {
+                return Err(meta.error("expected `permissions = [...]` or `parents = [...]`"));
+            };
+            let value = meta.value()?;
+            let content;
+            bracketed!(content in value);
+            list.extend(Punctuated::<Path, Token![,]>::parse_terminated(&content)?);
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+fn role_attributes(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("role"))
+}
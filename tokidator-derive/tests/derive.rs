@@ -0,0 +1,86 @@
+use tokidator::rbac::{Permission, PermissionSet, Predicate, Role, RoleSet};
+
+#[derive(Debug, Permission)]
+enum TestPermission {
+    Read,
+    Write = 4,
+    Admin = 2,
+}
+
+#[derive(Debug, Role)]
+#[role(permission = TestPermission)]
+enum TestRole {
+    #[role(permissions = [Read])]
+    Viewer,
+    #[role(permissions = [Read, TestPermission::Write])]
+    Editor,
+    Guest,
+    #[role(permissions = [TestPermission::Write], parents = [Viewer])]
+    Writer,
+    #[role(permissions = [Admin], parents = [TestRole::Editor])]
+    Admin,
+}
+
+#[test]
+fn permission_should_order_by_discriminant() {
+    use TestPermission::*;
+    assert!(Read < Admin);
+    assert!(Admin < Write);
+    assert_eq!(
+        PermissionSet::from([Write, Admin, Read])
+            .iter()
+            .collect::<Vec<_>>(),
+        [&Read, &Admin, &Write]
+    );
+}
+
+#[test]
+fn permission_should_convert_from_and_to_primitive() {
+    use num_traits::{FromPrimitive, ToPrimitive};
+    use TestPermission::*;
+    assert_eq!(Write.to_usize(), Some(4));
+    assert_eq!(TestPermission::from_usize(2), Some(Admin));
+    assert_eq!(TestPermission::from_usize(1), None);
+    assert_eq!(TestPermission::from_i64(-1), None);
+}
+
+#[test]
+fn permission_set_should_round_trip() {
+    use TestPermission::*;
+    let ps = PermissionSet::from([Read, Write]);
+    let parsed = PermissionSet::<TestPermission>::parse_from_bytes(&ps.to_bytes()).unwrap();
+    assert!(Predicate::all([Read, Write]).satisfy(&parsed));
+    assert!(!Predicate::contains(Admin).satisfy(&parsed));
+}
+
+#[test]
+fn role_should_have_declared_permissions() {
+    use TestPermission::*;
+    assert_eq!(TestRole::Viewer.permissions(), &[Read]);
+    assert_eq!(TestRole::Editor.permissions(), &[Read, Write]);
+    assert!(TestRole::Guest.permissions().is_empty());
+
+    let rs: RoleSet<TestRole> = vec![TestRole::Viewer, TestRole::Editor].into();
+    assert!(Predicate::all([Read, Write]).satisfy(&rs.to_permission_set()));
+}
+
+#[test]
+fn role_should_have_declared_parents() {
+    assert!(TestRole::Guest.parents().is_empty());
+    assert!(TestRole::Editor.parents().is_empty());
+    assert_eq!(TestRole::Writer.parents(), &[TestRole::Viewer]);
+    assert_eq!(TestRole::Admin.parents(), &[TestRole::Editor]);
+}
+
+#[test]
+fn role_should_inherit_parent_permissions() {
+    use TestPermission::*;
+    assert_eq!(TestRole::Writer.permissions(), &[Write]);
+    let rs: RoleSet<TestRole> = vec![TestRole::Writer].into();
+    let ps = rs.to_permission_set();
+    assert!(Predicate::all([Read, Write]).satisfy(&ps));
+    assert!(!Predicate::contains(Admin).satisfy(&ps));
+
+    let rs: RoleSet<TestRole> = vec![TestRole::Admin].into();
+    assert!(Predicate::all([Read, Write, Admin]).satisfy(&rs.to_permission_set()));
+}
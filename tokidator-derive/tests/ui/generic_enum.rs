@@ -0,0 +1,9 @@
+use tokidator::rbac::Permission;
+
+#[derive(Permission)]
+enum Generic<T> {
+    Read,
+    Write(T),
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use tokidator::rbac::{Permission, Role};
+
+#[derive(Debug, Permission)]
+enum MyPermission {
+    Read,
+}
+
+#[derive(Debug, Role)]
+enum MyRole {
+    #[role(permissions = [Read])]
+    Viewer,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use tokidator::rbac::Permission;
+
+#[derive(Debug, Permission)]
+#[repr(i8)]
+enum Negative {
+    Read = -1,
+    Write,
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use tokidator::rbac::Permission;
+
+#[derive(Permission)]
+struct NotEnum;
+
+fn main() {}
@@ -0,0 +1,15 @@
+use tokidator::rbac::{Permission, Role};
+
+#[derive(Debug, Permission)]
+enum MyPermission {
+    Read,
+}
+
+#[derive(Debug, Role)]
+#[role(permission = MyPermission)]
+enum MyRole {
+    #[role(grants = [Read])]
+    Viewer,
+}
+
+fn main() {}
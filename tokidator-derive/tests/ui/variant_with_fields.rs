@@ -0,0 +1,9 @@
+use tokidator::rbac::Permission;
+
+#[derive(Permission)]
+enum WithFields {
+    Read,
+    Write(u8),
+}
+
+fn main() {}